@@ -0,0 +1,59 @@
+//! Styling of a large generated document with a large style sheet.
+//!
+//! Run with `cargo bench --bench style`.
+use criterion::{criterion_group, criterion_main, Criterion};
+use wbp::css::{self, media::Device, StyleSheet};
+use wbp::html::Html;
+use wbp::style::style_tree;
+
+/// Document of `items` list items spread over sections, every item has a few classes.
+fn document(items: usize) -> String {
+    let mut html = String::from("<html><body>");
+    for section in 0..items / 50 {
+        html.push_str(&format!(r#"<div id="section-{section}" class="section"><ul>"#));
+        for item in 0..50 {
+            html.push_str(&format!(
+                r#"<li class="item item-{} kind-{}"><a href="/{item}">Item <em>{item}</em></a></li>"#,
+                item % 10,
+                item % 3,
+            ));
+        }
+        html.push_str("</ul></div>");
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+/// Style sheet with `rules` rules keyed by ids, classes and tag names, as generated style sheets
+/// of big sites are.
+fn stylesheet(rules: usize) -> StyleSheet {
+    let mut source = String::new();
+    for rule in 0..rules {
+        let selector = match rule % 4 {
+            0 => format!("#section-{rule}"),
+            1 => format!(".section .item-{} a", rule % 10),
+            2 => format!(".unused-{rule} > li"),
+            _ => format!("ul > li.kind-{}", rule % 3),
+        };
+        source.push_str(&format!("{selector} {{ padding-left: {}px }}\n", rule % 7));
+    }
+    css::parse_stylesheet(&source).0
+}
+
+fn style_large_document(c: &mut Criterion) {
+    let html = Html::parse_document(&document(2000));
+    let stylesheet = stylesheet(1000);
+    let user_stylesheet = StyleSheet::new();
+    let device = Device::default();
+
+    c.bench_function("style 2000 items with 1000 rules", |b| {
+        b.iter(|| style_tree(&html.tree, &stylesheet, &user_stylesheet, &device))
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = style_large_document
+}
+criterion_main!(benches);
@@ -21,6 +21,32 @@ pub(crate) struct Property {
     values: String,
     initial_value: String,
     inherited: bool,
+    /// Properties set by this shorthand property.
+    #[serde(default)]
+    longhands: Vec<String>,
+}
+
+impl Property {
+    /// Whether the property is a shorthand for the four sides of a box, e.g. `margin` or
+    /// `border-color`, with longhands listed clockwise from the top.
+    fn sets_box_sides(&self) -> bool {
+        let sides = ["top", "right", "bottom", "left"];
+        self.longhands.len() == sides.len()
+            && self
+                .longhands
+                .iter()
+                .zip(sides)
+                .all(|(longhand, side)| longhand.split('-').any(|part| part == side))
+    }
+
+    /// Whether `inherit` keyword is one of the top level alternatives of the property value.
+    fn accepts_inherit(&self) -> bool {
+        let value_definition_syntax = css_value_parser().parse(&self.values).unwrap();
+        match value_definition_syntax {
+            VDS::Choice(options) => options.contains(&VDS::Keyword("inherit")),
+            _ => false,
+        }
+    }
 }
 
 pub(crate) type Properties = Vec<Property>;
@@ -61,12 +87,15 @@ pub(crate) fn gen_struct_parser_body(ir_struct: &IrStruct) -> TokenStream {
                 // TODO: Move this loop to function.
                 quote! {
                     input.try_parse(|input| {
-                        let mut i = #min;
                         let mut res = vec![];
 
-                        while let (Ok(v), true) = (#inner_parser, i <= #max) {
-                            res.push(v);
-                            i += 1;
+                        // Check the count first, so that a value after the last allowed one is
+                        // left in the input.
+                        while res.len() < #max {
+                            match #inner_parser {
+                                Ok(v) => res.push(v),
+                                Err(_) => break,
+                            }
                         }
 
                         if (#min <= res.len() && res.len() <= #max) {
@@ -198,11 +227,12 @@ pub(crate) fn gen_enum_parser_body(IrEnum { name, variants }: &IrEnum) -> TokenS
                         // TODO: Move this loop to function.
                         quote! {
                             input.try_parse(|input| {
-                                let mut i = #min;
                                 let mut res = vec![];
-                                while let (Ok(v), true) = (#inner_parser, i <= #max) {
-                                    res.push(v);
-                                    i += 1;
+                                while res.len() < #max {
+                                    match #inner_parser {
+                                        Ok(v) => res.push(v),
+                                        Err(_) => break,
+                                    }
                                 }
                                 if (#min <= res.len() && res.len() <= #max) {
                                     Ok(#enum_name::#variant_name(res))
@@ -274,6 +304,52 @@ pub(crate) fn gen_parser(item: &IrItem) -> TokenStream {
     }
 }
 
+/// Resolve lengths in every field or variant payload, other values are left as they are.
+pub(crate) fn gen_resolve_lengths(item: &IrItem) -> TokenStream {
+    let (name, body) = match item {
+        IrItem::Struct(IrStruct { name, fields, .. }) => {
+            let fields = fields.iter().map(|IrField { name, .. }| {
+                let name = format_ident!("{}", name.to_case(Case::Snake));
+                quote! {
+                    self.#name.resolve_lengths(font_size);
+                }
+            });
+            (name, quote! { #(#fields)* })
+        }
+        IrItem::Enum(IrEnum { name, variants }) => {
+            let enum_name = format_ident!("{}", name.to_case(Case::Pascal));
+            let arms = variants.iter().filter(|v| v.payload.is_some()).map(
+                |IrVariant { name, .. }| {
+                    let variant = format_ident!("{}", name.to_case(Case::Pascal));
+                    quote! {
+                        #enum_name::#variant(v) => v.resolve_lengths(font_size),
+                    }
+                },
+            );
+            (
+                name,
+                quote! {
+                    #[allow(unreachable_patterns)]
+                    match self {
+                        #(#arms)*
+                        _ => {}
+                    }
+                },
+            )
+        }
+    };
+    let name = format_ident!("{}", name.to_case(Case::Pascal));
+
+    quote! {
+        impl ResolveLengths for #name {
+            #[allow(unused_variables)]
+            fn resolve_lengths(&mut self, font_size: f32) {
+                #body
+            }
+        }
+    }
+}
+
 pub(crate) fn gen_type(ty: &IrType) -> TokenStream {
     match ty {
         IrType::Leaf(s) | IrType::Named(s) => {
@@ -377,6 +453,8 @@ pub(crate) fn gen_declaration(item: &IrItem) -> TokenStream {
 pub(crate) fn gen_property(i: u8, prop: &Property) -> TokenStream {
     let name = prop.name.to_case(Case::Pascal);
     let ident = format_ident!("{}", name);
+    let prop_name = &prop.name;
+    let inherited = prop.inherited;
 
     // // We just unwrap, since it just a macro and not an actual code that will be executed in the browser.
     let value_definition_syntax = css_value_parser().parse(&prop.values).unwrap();
@@ -385,6 +463,7 @@ pub(crate) fn gen_property(i: u8, prop: &Property) -> TokenStream {
     let _ = build_ir(&prop.name, &value_definition_syntax, &mut items);
 
     let parsers = items.iter().map(gen_parser);
+    let resolvers = items.iter().map(gen_resolve_lengths);
 
     let decls = items
         .iter()
@@ -410,9 +489,13 @@ pub(crate) fn gen_property(i: u8, prop: &Property) -> TokenStream {
         #[sealed]
         impl Property for #ident {
             const ID: PropIndex = #i;
+            const NAME: &'static str = #prop_name;
+            const INHERITED: bool = #inherited;
         }
 
         #(#parsers)*
+
+        #(#resolvers)*
     }
 }
 
@@ -534,6 +617,94 @@ pub fn css_properties(tokens: proc_macro::TokenStream) -> proc_macro::TokenStrea
         }
     });
 
+    let props_count = props_json.len();
+
+    let is_inherit_arms = props_json.iter().enumerate().map(|(i, prop)| {
+        let variant = format_ident!("{}", prop.name.to_case(Case::Snake));
+        let ty = format_ident!("{}", prop.name.to_case(Case::Pascal));
+        let i = i as u8;
+        if prop.accepts_inherit() {
+            quote! {
+                #i => matches!(&*self.#variant, #ty::Inherit),
+            }
+        } else {
+            quote! {
+                #i => false,
+            }
+        }
+    });
+
+    let resolve_lengths_arms = props_names.iter().enumerate().map(|(i, &name)| {
+        let variant = format_ident!("{}", name.to_case(Case::Snake));
+        let i = i as u8;
+        quote! {
+            #i => self.#variant.resolve_lengths(font_size),
+        }
+    });
+
+    let prop_index_arms = props_names.iter().enumerate().map(|(i, &name)| {
+        let i = i as u8;
+        quote! {
+            #name => Some(#i),
+        }
+    });
+
+    let inherited_arms = props_json.iter().enumerate().map(|(i, prop)| {
+        let i = i as u8;
+        let inherited = prop.inherited;
+        quote! {
+            #i => #inherited,
+        }
+    });
+
+    let names = &props_names;
+    let shorthand_arms = props_json
+        .iter()
+        .filter(|prop| !prop.longhands.is_empty())
+        .map(|prop| {
+            let name = &prop.name;
+            let ty = format_ident!("{}", name.to_case(Case::Pascal));
+            let longhands = prop.longhands.iter().map(|longhand| {
+                assert!(
+                    names.contains(&longhand.as_str()),
+                    "Unknown longhand `{longhand}` of `{name}`."
+                );
+                let longhand = format_ident!("{}", longhand.to_case(Case::Pascal));
+                quote! {
+                    (#longhand::ID, |input| #longhand::parse(input).map(PropUnion::from))
+                }
+            });
+            let expand = if prop.sets_box_sides() {
+                quote! { expand_box_sides }
+            } else {
+                quote! { expand_any_order }
+            };
+            quote! {
+                #name => {
+                    // Shorthand grammar is checked first, longhands take their parts of the value.
+                    let start = input.state();
+                    #ty::parse(input)?;
+                    input.expect_exhausted().map_err(|_| ())?;
+                    input.reset(&start);
+                    #expand(input, &[#(#longhands),*])
+                }
+            }
+        });
+
+    let initial_values = props_json
+        .iter()
+        .filter(|prop| !prop.initial_value.is_empty())
+        .map(|prop| {
+            let ty = format_ident!("{}", prop.name.to_case(Case::Pascal));
+            let initial_value = &prop.initial_value;
+            let error = format!("invalid initial value of `{}`", prop.name);
+            quote! {
+                let mut input = cssparser::ParserInput::new(#initial_value);
+                let mut parser = Parser::new(&mut input);
+                props.set(#ty::parse(&mut parser).expect(#error));
+            }
+        });
+
     let eq_comps = props_names.iter().map(|name| {
         let struct_name_id = format_ident!("{}", name.to_case(Case::Pascal));
         quote! {
@@ -557,9 +728,72 @@ pub fn css_properties(tokens: proc_macro::TokenStream) -> proc_macro::TokenStrea
                 #prop_union_parse_body
             }
 
+            /// Parse value of property `prop_name` up to the end of `input`. Shorthands are
+            /// expanded into their longhands, other properties give a single value.
+            pub fn parse_declaration<'i, 't>(
+                prop_name: &str,
+                input: &mut Parser<'i, 't>,
+            ) -> Result<Vec<(PropIndex, Self)>, ()> {
+                match prop_name.to_lowercase().as_str() {
+                    #(#shorthand_arms)*
+                    _ => {
+                        let value = Self::parse(prop_name, input)?;
+                        input.expect_exhausted().map_err(|_| ())?;
+                        Ok(vec![value])
+                    }
+                }
+            }
+
             pub unsafe fn clone_variant(&self, idx: PropIndex) -> Self {
                 #union_clone_body
             }
+
+            /// Whether value of property `idx` is the `inherit` keyword.
+            pub unsafe fn is_inherit(&self, idx: PropIndex) -> bool {
+                match idx {
+                    #(#is_inherit_arms)*
+                    _ => unreachable!(),
+                }
+            }
+
+            /// Resolve lengths in value of property `idx`, see [`ResolveLengths`].
+            pub unsafe fn resolve_lengths(&mut self, idx: PropIndex, font_size: f32) {
+                match idx {
+                    #(#resolve_lengths_arms)*
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        /// Number of known properties, their indices go from zero up to this number.
+        pub const PROPS_COUNT: usize = #props_count;
+
+        /// Index of the property called `name`, if it is known.
+        pub fn prop_index(name: &str) -> Option<PropIndex> {
+            match name.to_lowercase().as_str() {
+                #(#prop_index_arms)*
+                _ => None,
+            }
+        }
+
+        /// Whether property `idx` is inherited by default.
+        pub fn is_inherited(idx: PropIndex) -> bool {
+            match idx {
+                #(#inherited_arms)*
+                _ => unreachable!(),
+            }
+        }
+
+
+        impl Props {
+            /// Initial values of all properties that have them, shorthands have none.
+            fn initial_values() -> Props {
+                let mut props = Props::new();
+
+                #(#initial_values)*
+
+                props
+            }
         }
 
         impl fmt::Debug for Props {
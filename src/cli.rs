@@ -0,0 +1,73 @@
+//! Command line interface of the browser.
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use log::LevelFilter;
+use url::Url;
+
+#[derive(Parser, Debug)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Path or `file://` URL of the document to open.
+    #[arg(value_parser = parse_document, default_value = "test.html")]
+    pub document: PathBuf,
+
+    /// Initial width of the window in pixels.
+    #[arg(long, default_value_t = 800)]
+    pub width: u32,
+
+    /// Initial height of the window in pixels.
+    #[arg(long, default_value_t = 600)]
+    pub height: u32,
+
+    /// Style sheet applied to every page with the user origin, e.g. to enlarge fonts.
+    #[arg(long, global = true)]
+    pub user_stylesheet: Option<PathBuf>,
+
+    /// Maximum level of log messages. Takes precedence over `RUST_LOG`.
+    #[arg(long, global = true)]
+    pub log_level: Option<LevelFilter>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Render page without opening a window and save it as PNG image.
+    Render {
+        /// Path or `file://` URL of the document to render.
+        #[arg(value_parser = parse_document)]
+        input: PathBuf,
+
+        /// Viewport width in pixels.
+        #[arg(long, default_value_t = 800)]
+        width: u32,
+
+        /// Viewport height in pixels. Height of the document is used when omitted.
+        #[arg(long)]
+        height: Option<u32>,
+
+        /// Where to save resulting PNG image.
+        #[arg(long, short)]
+        out: PathBuf,
+    },
+}
+
+/// Turn document argument into a path on the disk.
+///
+/// Anything that parses as an URL is treated as such, and only `file` scheme is supported for
+/// now. Everything else is treated as a plain path.
+fn parse_document(s: &str) -> Result<PathBuf, String> {
+    match Url::parse(s) {
+        Ok(url) if url.scheme() == "file" => url
+            .to_file_path()
+            .map_err(|_| format!("`{url}` does not point to a local file")),
+        // Single letter scheme is most likely a drive letter of a Windows path.
+        Ok(url) if url.scheme().len() > 1 => Err(format!(
+            "unsupported URL scheme `{}`, only `file` URLs can be opened",
+            url.scheme()
+        )),
+        _ => Ok(PathBuf::from(s)),
+    }
+}
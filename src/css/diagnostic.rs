@@ -0,0 +1,119 @@
+//! Problems found in style sheets.
+//!
+//! Invalid parts of a style sheet are skipped as CSS requires, every skipped part is reported as a
+//! [`Diagnostic`] pointing to its place in the source.
+use std::fmt::{self, Display};
+
+use cssparser::SourceLocation;
+use ego_tree::NodeId;
+use url::Url;
+
+/// Single problem in a style sheet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Line of the problem, starting from 1.
+    pub line: u32,
+    /// Column of the problem, starting from 1.
+    pub column: u32,
+    /// Name of the property, if the problem is in a declaration.
+    pub property: Option<String>,
+    /// Offending source text, e.g. value of a declaration or prelude of a rule.
+    pub value: String,
+    pub reason: Reason,
+}
+
+/// Why a part of a style sheet was skipped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// Property with this name is not supported.
+    UnknownProperty,
+    /// Value does not match the grammar of the property.
+    InvalidValue,
+    /// Value is valid, but not supported by the engine yet, the initial value is used instead.
+    UnsupportedValue,
+    /// Selector of a rule can not be parsed.
+    InvalidSelector,
+    /// At rule with this name is not supported.
+    UnsupportedAtRule,
+    /// `@import` after style rules or inside of another rule.
+    MisplacedImport,
+    /// Text is not a valid declaration or rule at all, e.g. missing colon or brace.
+    InvalidSyntax,
+}
+
+/// Style sheet of a document a diagnostic was found in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StyleSource {
+    /// Content of a `<style>` element.
+    StyleElement(NodeId),
+    /// Value of a `style` attribute of an element.
+    StyleAttribute(NodeId),
+    /// Linked or imported style sheet.
+    Url(Url),
+}
+
+/// Problem in one of the style sheets of a document, see [`crate::css::from_document`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentDiagnostic {
+    pub source: StyleSource,
+    /// Position of the problem is relative to the start of its source.
+    pub diagnostic: Diagnostic,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(
+        location: SourceLocation,
+        property: Option<String>,
+        value: impl Into<String>,
+        reason: Reason,
+    ) -> Self {
+        Self {
+            // Lines of `cssparser` start from 0, while columns start from 1.
+            line: location.line + 1,
+            column: location.column,
+            property,
+            value: value.into(),
+            reason,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.reason)?;
+        match &self.property {
+            Some(property) => write!(f, " `{property}: {}`", self.value),
+            None => write!(f, " `{}`", self.value),
+        }
+    }
+}
+
+impl Display for StyleSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StyleSource::StyleElement(_) => f.write_str("<style>"),
+            StyleSource::StyleAttribute(_) => f.write_str("style attribute"),
+            StyleSource::Url(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+impl Display for DocumentDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.source, self.diagnostic)
+    }
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Reason::UnknownProperty => "unknown property",
+            Reason::InvalidValue => "invalid value",
+            Reason::UnsupportedValue => "unsupported value",
+            Reason::InvalidSelector => "invalid selector",
+            Reason::UnsupportedAtRule => "unsupported at rule",
+            Reason::MisplacedImport => "misplaced @import",
+            Reason::InvalidSyntax => "invalid syntax",
+        })
+    }
+}
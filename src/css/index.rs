@@ -0,0 +1,130 @@
+//! Index of style sheet rules for selector matching.
+//!
+//! Most selectors can only match elements with a certain id, class or name, which is known from
+//! the rightmost compound selector, e.g. `.item` in `ul > li.item`. Rules are put into buckets by
+//! these keys, so the cascade tests only rules from the buckets of an element instead of every rule
+//! of the style sheet.
+use std::collections::HashMap;
+
+use selectors::parser::{Combinator, Component, Selector};
+
+use crate::css::Rule;
+use crate::html::Element;
+use crate::selector::Simple;
+
+/// Positions of rules in a style sheet bucketed by keys of their selectors.
+#[derive(Clone, Debug, Default)]
+pub struct RuleIndex {
+    ids: HashMap<String, Vec<usize>>,
+    classes: HashMap<String, Vec<usize>>,
+    local_names: HashMap<String, Vec<usize>>,
+    /// Rules with a selector that has none of the keys above, e.g. `*` or `[href]`.
+    universal: Vec<usize>,
+}
+
+/// The most selective key of a selector.
+enum Key<'a> {
+    Id(&'a str),
+    Class(&'a str),
+    LocalName(&'a str),
+    Universal,
+}
+
+impl RuleIndex {
+    /// Index `rules` by every selector of each rule.
+    pub fn new(rules: &[Rule]) -> Self {
+        let mut index = Self::default();
+        for (position, rule) in rules.iter().enumerate() {
+            for selector in rule.selectors.selectors.slice() {
+                let bucket = match key(selector) {
+                    Key::Id(id) => index.ids.entry(id.to_owned()).or_default(),
+                    Key::Class(class) => index.classes.entry(class.to_owned()).or_default(),
+                    Key::LocalName(name) => index.local_names.entry(name.to_owned()).or_default(),
+                    Key::Universal => &mut index.universal,
+                };
+                // Selectors of a rule often share a key, e.g. `h1, h1 *`.
+                if bucket.last() != Some(&position) {
+                    bucket.push(position);
+                }
+            }
+        }
+        index
+    }
+
+    /// Positions of rules that may match `element` in ascending order. Rules not in the list do
+    /// not match the element or its pseudo-elements.
+    pub fn candidates(&self, element: &Element) -> Vec<usize> {
+        let mut positions = self.universal.clone();
+        if let Some(rules) = element.id().and_then(|id| self.ids.get(id)) {
+            positions.extend(rules);
+        }
+        for class in element.classes() {
+            if let Some(rules) = self.classes.get(class) {
+                positions.extend(rules);
+            }
+        }
+        if let Some(rules) = self.local_names.get(element.name()) {
+            positions.extend(rules);
+        }
+
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+    }
+}
+
+/// Key of the rightmost compound selector of `selector`, which the element itself must match.
+///
+/// Pseudo-elements are a compound of their own, the key is taken from the element's compound
+/// before them.
+fn key(selector: &Selector<Simple>) -> Key<'_> {
+    let mut key = Key::Universal;
+    let mut iter = selector.iter();
+    loop {
+        for component in &mut iter {
+            match component {
+                Component::ID(id) => return Key::Id(&id.0),
+                Component::Class(class) if !matches!(key, Key::Class(_)) => {
+                    key = Key::Class(&class.0)
+                }
+                Component::LocalName(name) if matches!(key, Key::Universal) => {
+                    key = Key::LocalName(&name.lower_name.0)
+                }
+                _ => {}
+            }
+        }
+        if iter.next_sequence() != Some(Combinator::PseudoElement) {
+            return key;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_stylesheet;
+    use crate::html::Html;
+    use crate::selector::SelectorGroup;
+
+    #[test]
+    fn rules_are_bucketed_by_rightmost_compound() {
+        let (stylesheet, _) = parse_stylesheet(
+            "#main {} .a.b {} div.a {} P {} * {} div p {} p::before {} .b:hover {} #x, .a {}",
+        );
+        let index = RuleIndex::new(&stylesheet);
+        assert_eq!(index.ids["main"], [0]);
+        assert_eq!(index.ids["x"], [8]);
+        assert_eq!(index.classes["a"], [1, 2, 8]);
+        assert_eq!(index.classes["b"], [7]);
+        assert_eq!(index.local_names["p"], [3, 5, 6]);
+        assert_eq!(index.universal, [4]);
+
+        let html = Html::parse_fragment(r#"<div id="main" class="b a"><p class="a"></p></div>"#);
+        let candidates = |selector| {
+            let selector = SelectorGroup::parse(selector).unwrap();
+            index.candidates(html.select(&selector).next().unwrap().value())
+        };
+        assert_eq!(candidates("div"), [0, 1, 2, 4, 7, 8]);
+        assert_eq!(candidates("p"), [1, 2, 3, 4, 5, 6, 8]);
+    }
+}
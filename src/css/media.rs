@@ -0,0 +1,408 @@
+//! Media queries of `@media` and `@import` rules and of `media` attributes.
+//!
+//! Supported are media types, `width` and `height` with their `min-`/`max-` prefixes and range
+//! syntax, `orientation` and `prefers-color-scheme`. Queries that fail to parse never match, as
+//! required by the [specification](https://www.w3.org/TR/mediaqueries-4/#error-handling).
+use cssparser::{Delimiter, ParseError, Parser, ParserInput, Token};
+
+use super::props::{ParseableProperty, ResolveLengths};
+use super::values::{AbsoluteSize, Length};
+
+/// Color scheme preferred by the user.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// Output device media queries are evaluated against.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Device {
+    /// Width of the viewport in px.
+    pub width: f32,
+    /// Height of the viewport in px.
+    pub height: f32,
+    pub color_scheme: ColorScheme,
+}
+
+/// Comma separated list of media queries, matches if any of them does.
+///
+/// Empty list matches every device.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaList(pub Vec<MediaQuery>);
+
+/// Single media query: optionally negated media type and features joined with `and`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaQuery {
+    pub negated: bool,
+    pub media_type: MediaType,
+    pub features: Vec<MediaFeature>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MediaType {
+    All,
+    Screen,
+    Print,
+    /// Any other media type, such types never match.
+    Other(String),
+}
+
+/// Condition on a single property of the device.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MediaFeature {
+    /// Comparison of the viewport width with a length in px.
+    Width(Comparison, f32),
+    /// Comparison of the viewport height with a length in px.
+    Height(Comparison, f32),
+    Orientation(Orientation),
+    PrefersColorScheme(ColorScheme),
+}
+
+/// How the value of a device property relates to the value in the query.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+impl MediaList {
+    /// Parse media query list up to the end of `input`.
+    pub fn parse(input: &mut Parser) -> Self {
+        let mut queries = Vec::new();
+        if input.is_exhausted() {
+            return Self(queries);
+        }
+
+        loop {
+            let query = input
+                .parse_until_before(Delimiter::Comma, MediaQuery::parse)
+                .unwrap_or_else(|_| MediaQuery::never());
+            queries.push(query);
+            if input.next().is_err() {
+                break;
+            }
+        }
+
+        Self(queries)
+    }
+
+    /// Parse media query list from text, e.g. value of a `media` attribute.
+    pub fn parse_str(text: &str) -> Self {
+        let mut input = ParserInput::new(text);
+        Self::parse(&mut Parser::new(&mut input))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn matches(&self, device: &Device) -> bool {
+        self.is_empty() || self.0.iter().any(|query| query.matches(device))
+    }
+}
+
+impl MediaQuery {
+    /// Query that does not match any device, invalid queries are replaced with it.
+    pub fn never() -> Self {
+        Self {
+            negated: true,
+            media_type: MediaType::All,
+            features: Vec::new(),
+        }
+    }
+
+    fn parse<'i>(input: &mut Parser<'i, '_>) -> Result<Self, ParseError<'i, ()>> {
+        let mut query = Self {
+            negated: false,
+            media_type: MediaType::All,
+            features: Vec::new(),
+        };
+
+        match input.next()?.clone() {
+            Token::ParenthesisBlock => {
+                let features = input.parse_nested_block(parse_feature)?;
+                query.features.extend(features);
+            }
+            Token::Ident(mut ident) => {
+                if ident.eq_ignore_ascii_case("not") {
+                    query.negated = true;
+                    ident = input.expect_ident()?.clone();
+                } else if ident.eq_ignore_ascii_case("only") {
+                    ident = input.expect_ident()?.clone();
+                }
+                query.media_type = match ident.to_ascii_lowercase().as_str() {
+                    "all" => MediaType::All,
+                    "screen" => MediaType::Screen,
+                    "print" => MediaType::Print,
+                    "not" | "only" | "and" | "or" => {
+                        return Err(input.new_custom_error(()));
+                    }
+                    other => MediaType::Other(other.to_owned()),
+                };
+            }
+            token => return Err(input.new_unexpected_token_error(token)),
+        }
+
+        while !input.is_exhausted() {
+            input.expect_ident_matching("and")?;
+            input.expect_parenthesis_block()?;
+            let features = input.parse_nested_block(parse_feature)?;
+            query.features.extend(features);
+        }
+
+        Ok(query)
+    }
+
+    pub fn matches(&self, device: &Device) -> bool {
+        let matches = self.media_type.matches()
+            && self.features.iter().all(|feature| feature.matches(device));
+        matches != self.negated
+    }
+}
+
+impl MediaType {
+    /// Whether the engine, which always renders to a screen, is of this type.
+    pub fn matches(&self) -> bool {
+        matches!(self, MediaType::All | MediaType::Screen)
+    }
+}
+
+impl MediaFeature {
+    pub fn matches(&self, device: &Device) -> bool {
+        match *self {
+            MediaFeature::Width(comparison, width) => comparison.holds(device.width, width),
+            MediaFeature::Height(comparison, height) => comparison.holds(device.height, height),
+            MediaFeature::Orientation(orientation) => {
+                let actual = if device.height >= device.width {
+                    Orientation::Portrait
+                } else {
+                    Orientation::Landscape
+                };
+                actual == orientation
+            }
+            MediaFeature::PrefersColorScheme(color_scheme) => device.color_scheme == color_scheme,
+        }
+    }
+}
+
+impl Comparison {
+    /// Whether `actual` value relates to `value` as required.
+    pub fn holds(self, actual: f32, value: f32) -> bool {
+        match self {
+            Comparison::Lt => actual < value,
+            Comparison::Le => actual <= value,
+            Comparison::Eq => actual == value,
+            Comparison::Ge => actual >= value,
+            Comparison::Gt => actual > value,
+        }
+    }
+
+    /// Comparison with swapped sides, e.g. `a < b` becomes `b > a`.
+    fn flip(self) -> Self {
+        match self {
+            Comparison::Lt => Comparison::Gt,
+            Comparison::Le => Comparison::Ge,
+            Comparison::Eq => Comparison::Eq,
+            Comparison::Ge => Comparison::Le,
+            Comparison::Gt => Comparison::Lt,
+        }
+    }
+
+    fn parse<'i>(input: &mut Parser<'i, '_>) -> Result<Self, ParseError<'i, ()>> {
+        let comparison = match input.next()?.clone() {
+            Token::Delim('<') => Comparison::Lt,
+            Token::Delim('>') => Comparison::Gt,
+            Token::Delim('=') => return Ok(Comparison::Eq),
+            token => return Err(input.new_unexpected_token_error(token)),
+        };
+        // `=` must immediately follow `<` or `>`.
+        let or_equal = input
+            .try_parse(|input| match input.next_including_whitespace() {
+                Ok(Token::Delim('=')) => Ok(()),
+                _ => Err(()),
+            })
+            .is_ok();
+
+        Ok(match (comparison, or_equal) {
+            (Comparison::Lt, true) => Comparison::Le,
+            (Comparison::Gt, true) => Comparison::Ge,
+            (comparison, _) => comparison,
+        })
+    }
+}
+
+/// Parse contents of parentheses, which is a single feature or a range with two comparisons.
+fn parse_feature<'i>(input: &mut Parser<'i, '_>) -> Result<Vec<MediaFeature>, ParseError<'i, ()>> {
+    // Range with the value first, e.g. `(400px <= width < 800px)`.
+    if let Ok(value) = input.try_parse(parse_length) {
+        let comparison = Comparison::parse(input)?.flip();
+        let name = input.expect_ident()?.clone();
+        let mut features = vec![range_feature(input, &name, comparison, value)?];
+        if !input.is_exhausted() {
+            let comparison = Comparison::parse(input)?;
+            let value = parse_length(input)?;
+            features.push(range_feature(input, &name, comparison, value)?);
+        }
+        return expect_end(input, features);
+    }
+
+    let name = input.expect_ident()?.clone();
+    if input.try_parse(|input| input.expect_colon()).is_err() {
+        // Range with the name first, e.g. `(width >= 600px)`.
+        let comparison = Comparison::parse(input)?;
+        let value = parse_length(input)?;
+        let feature = range_feature(input, &name, comparison, value)?;
+        return expect_end(input, vec![feature]);
+    }
+
+    let name = name.to_ascii_lowercase();
+    let feature = match name.as_str() {
+        "width" | "height" | "min-width" | "min-height" | "max-width" | "max-height" => {
+            let value = parse_length(input)?;
+            let (comparison, name) = if let Some(name) = name.strip_prefix("min-") {
+                (Comparison::Ge, name)
+            } else if let Some(name) = name.strip_prefix("max-") {
+                (Comparison::Le, name)
+            } else {
+                (Comparison::Eq, name.as_str())
+            };
+            range_feature(input, name, comparison, value)?
+        }
+        "orientation" => {
+            let location = input.current_source_location();
+            let value = input.expect_ident()?;
+            MediaFeature::Orientation(match value.to_ascii_lowercase().as_str() {
+                "portrait" => Orientation::Portrait,
+                "landscape" => Orientation::Landscape,
+                _ => return Err(location.new_custom_error(())),
+            })
+        }
+        "prefers-color-scheme" => {
+            let location = input.current_source_location();
+            let value = input.expect_ident()?;
+            MediaFeature::PrefersColorScheme(match value.to_ascii_lowercase().as_str() {
+                "light" => ColorScheme::Light,
+                "dark" => ColorScheme::Dark,
+                _ => return Err(location.new_custom_error(())),
+            })
+        }
+        _ => return Err(input.new_custom_error(())),
+    };
+    expect_end(input, vec![feature])
+}
+
+/// Make sure that nothing follows the parsed features.
+fn expect_end<'i>(
+    input: &mut Parser<'i, '_>,
+    features: Vec<MediaFeature>,
+) -> Result<Vec<MediaFeature>, ParseError<'i, ()>> {
+    input.expect_exhausted()?;
+    Ok(features)
+}
+
+/// Feature comparing device dimension `name` with `value`.
+fn range_feature<'i>(
+    input: &Parser<'i, '_>,
+    name: &str,
+    comparison: Comparison,
+    value: f32,
+) -> Result<MediaFeature, ParseError<'i, ()>> {
+    if name.eq_ignore_ascii_case("width") {
+        Ok(MediaFeature::Width(comparison, value))
+    } else if name.eq_ignore_ascii_case("height") {
+        Ok(MediaFeature::Height(comparison, value))
+    } else {
+        Err(input.new_custom_error(()))
+    }
+}
+
+/// Parse length in px. Relative lengths refer to the initial font size.
+fn parse_length<'i>(input: &mut Parser<'i, '_>) -> Result<f32, ParseError<'i, ()>> {
+    let mut length = Length::parse(input).map_err(|()| input.new_custom_error(()))?;
+    length.resolve_lengths(AbsoluteSize::Medium.to_px());
+    Ok(length.to_px())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LANDSCAPE: Device = Device {
+        width: 800.0,
+        height: 600.0,
+        color_scheme: ColorScheme::Light,
+    };
+
+    fn matches(query: &str, device: &Device) -> bool {
+        MediaList::parse_str(query).matches(device)
+    }
+
+    #[test]
+    fn features_are_parsed() {
+        assert_eq!(
+            MediaList::parse_str("only screen and (min-width: 2em), not print"),
+            MediaList(vec![
+                MediaQuery {
+                    negated: false,
+                    media_type: MediaType::Screen,
+                    features: vec![MediaFeature::Width(Comparison::Ge, 32.0)],
+                },
+                MediaQuery {
+                    negated: true,
+                    media_type: MediaType::Print,
+                    features: vec![],
+                },
+            ])
+        );
+        assert_eq!(
+            MediaList::parse_str("(100px < height <= 1in)").0[0].features,
+            [
+                MediaFeature::Height(Comparison::Gt, 100.0),
+                MediaFeature::Height(Comparison::Le, 96.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn queries_are_evaluated_against_device() {
+        assert!(matches("", &LANDSCAPE));
+        assert!(matches("screen", &LANDSCAPE));
+        assert!(!matches("print", &LANDSCAPE));
+        assert!(matches("print, (orientation: landscape)", &LANDSCAPE));
+        assert!(matches(
+            "(max-width: 800px) and (min-height: 600px)",
+            &LANDSCAPE
+        ));
+        assert!(!matches("(width > 800px)", &LANDSCAPE));
+        assert!(matches("(500px <= width < 900px)", &LANDSCAPE));
+        assert!(!matches("(orientation: portrait)", &LANDSCAPE));
+        assert!(!matches("(prefers-color-scheme: dark)", &LANDSCAPE));
+
+        let dark = Device {
+            color_scheme: ColorScheme::Dark,
+            ..LANDSCAPE
+        };
+        assert!(matches("not all and (prefers-color-scheme: light)", &dark));
+    }
+
+    #[test]
+    fn invalid_queries_never_match() {
+        assert!(!matches("(unknown-feature: 1)", &LANDSCAPE));
+        assert!(!matches("(width: red)", &LANDSCAPE));
+        assert!(!matches("screen and", &LANDSCAPE));
+        assert!(!matches("tv", &LANDSCAPE));
+        // Other queries of the list are still evaluated.
+        assert!(matches("(width: red), screen", &LANDSCAPE));
+    }
+}
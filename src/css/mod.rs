@@ -1,48 +1,101 @@
+use std::ops::Deref;
 use std::sync::LazyLock;
 
 use cssparser::*;
-use log::error;
+use log::{debug, warn};
+use url::Url;
 
+use crate::css::diagnostic::{Diagnostic, DocumentDiagnostic, Reason, StyleSource};
+use crate::css::index::RuleIndex;
+use crate::css::media::{Device, MediaList};
 use crate::css::props::{PropIndex, PropUnion, Props};
-use crate::html::Html;
+use crate::html::{Element, Html};
+use crate::loader::ResourceLoader;
 use crate::selector::SelectorGroup;
 
+pub mod diagnostic;
+pub mod index;
+pub mod media;
 pub mod props;
 pub mod values;
 
 /// Engine default style sheet.
 pub static DEAFULT_STYLESHEET: LazyLock<StyleSheet> =
-    LazyLock::new(|| parse_stylesheet(include_str!("default.css")));
+    LazyLock::new(|| parse_stylesheet(include_str!("default.css")).0);
 
 #[derive(Debug, Clone)]
 pub struct Rule {
     pub selectors: SelectorGroup,
     pub important_declarations: Props,
     pub declarations: Props,
+    /// Media lists of enclosing `@media` and `@import` rules, all of them must match for the rule
+    /// to apply.
+    pub media: Vec<MediaList>,
 }
 
+impl Rule {
+    /// Whether media queries of the rule match `device`.
+    pub fn matches_media(&self, device: &Device) -> bool {
+        self.media.iter().all(|media| media.matches(device))
+    }
+}
+
+/// Declarations of an element's `style` attribute.
+#[derive(Debug, Clone, Default)]
+pub struct InlineStyle {
+    pub important_declarations: Props,
+    pub declarations: Props,
+}
+
+/// Top level item of a style sheet, as produced by [`RuleParser`].
+pub enum StyleSheetItem {
+    /// URL of an `@import` rule as written in the style sheet and its media list.
+    Import(String, MediaList),
+    Rule(Rule),
+    /// Rules of a `@media` rule, they already have its media list.
+    Media(Vec<Rule>),
+}
+
+/// Prelude of a supported at rule.
+pub enum AtRulePrelude {
+    Import(String, MediaList),
+    Media(MediaList),
+}
+
+/// Declaration with its shorthand expanded into longhands.
 struct Declaration {
-    idx: PropIndex,
-    value: PropUnion,
+    longhands: Vec<(PropIndex, PropUnion)>,
     important: bool,
 }
 
-struct DeclParser;
-pub struct RuleParser;
+/// Parser of declarations, collecting diagnostics for the ones it keeps with a changed value.
+#[derive(Default)]
+struct DeclParser {
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Parser of style sheet rules, collecting diagnostics for skipped ones.
+#[derive(Default)]
+pub struct RuleParser {
+    diagnostics: Vec<Diagnostic>,
+    /// Style rule was already parsed, so following `@import` rules are invalid.
+    seen_rules: bool,
+}
 
 impl<'i> DeclarationParser<'i> for DeclParser {
     type Declaration = Declaration;
-    type Error = CowRcStr<'i>;
+    type Error = Diagnostic;
 
     fn parse_value<'t>(
         &mut self,
         name: CowRcStr<'i>,
         input: &mut Parser<'i, 't>,
-        _declaration_start: &ParserState,
-    ) -> Result<Declaration, ParseError<'i, CowRcStr<'i>>> {
+        declaration_start: &ParserState,
+    ) -> Result<Declaration, ParseError<'i, Diagnostic>> {
+        input.skip_whitespace();
         let location = input.current_source_location();
-
-        let mut value = vec![];
+        let value_start = input.position();
+        let mut value_end = value_start;
         let mut important = false;
 
         loop {
@@ -61,50 +114,180 @@ impl<'i> DeclarationParser<'i> for DeclParser {
                     input.reset(&start);
                     token = input.next().unwrap().clone();
                 }
-                value.push(token.to_css_string());
+                if matches!(
+                    token,
+                    Token::Function(_)
+                        | Token::ParenthesisBlock
+                        | Token::SquareBracketBlock
+                        | Token::CurlyBracketBlock
+                ) {
+                    // Consume the block now, so that it is included in the value.
+                    input
+                        .parse_nested_block(|input| {
+                            while input.next().is_ok() {}
+                            Ok::<_, ParseError<'i, Diagnostic>>(())
+                        })
+                        .ok();
+                }
+                value_end = input.position();
             } else {
                 break;
             }
         }
 
-        let value = value.join(" ");
+        let source = input.slice(value_start..value_end).trim();
 
-        let mut dinput = ParserInput::new(&value);
+        let mut dinput = ParserInput::new(source);
         let mut parser = Parser::new(&mut dinput);
 
-        let (idx, value) = PropUnion::parse(&name, &mut parser).map_err(move |_| ParseError {
-            kind: ParseErrorKind::Custom(name),
-            location,
+        let mut longhands = PropUnion::parse_declaration(&name, &mut parser).map_err(|()| {
+            let diagnostic = match props::prop_index(&name) {
+                Some(_) => Diagnostic::new(
+                    location,
+                    Some(name.to_string()),
+                    source,
+                    Reason::InvalidValue,
+                ),
+                None => Diagnostic::new(
+                    declaration_start.source_location(),
+                    Some(name.to_string()),
+                    source,
+                    Reason::UnknownProperty,
+                ),
+            };
+            location.new_custom_error(diagnostic)
         })?;
 
+        for (idx, value) in &mut longhands {
+            if props::is_supported(*idx, value) {
+                continue;
+            }
+            let property = Some(name.to_string());
+            let diagnostic = Diagnostic::new(location, property, source, Reason::UnsupportedValue);
+            // Properties without initial value are skipped as a whole.
+            let Some(initial_value) = props::initial_value(*idx) else {
+                return Err(location.new_custom_error(diagnostic));
+            };
+            self.diagnostics.push(diagnostic);
+            *value = initial_value;
+        }
+
         Ok(Declaration {
-            idx,
-            value,
+            longhands,
             important,
         })
     }
 }
 
-impl<'i> AtRuleParser<'i> for DeclParser {
+impl AtRuleParser<'_> for DeclParser {
     type Prelude = ();
     type AtRule = Declaration;
-    type Error = CowRcStr<'i>;
+    type Error = Diagnostic;
 }
 
-impl<'i> QualifiedRuleParser<'i> for DeclParser {
+impl QualifiedRuleParser<'_> for DeclParser {
     type Prelude = ();
     type QualifiedRule = Declaration;
-    type Error = CowRcStr<'i>;
+    type Error = Diagnostic;
 }
 
-// Extend this when we will need at rules support.
-impl AtRuleParser<'_> for RuleParser {
-    type Prelude = ();
-    type AtRule = Rule;
-    type Error = ();
+// Extend this when we will need support for more at rules.
+impl<'i> AtRuleParser<'i> for RuleParser {
+    type Prelude = AtRulePrelude;
+    type AtRule = StyleSheetItem;
+    type Error = Diagnostic;
+
+    fn parse_prelude<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<AtRulePrelude, ParseError<'i, Diagnostic>> {
+        // Prelude starts right after the at keyword, which is a single token.
+        let mut location = input.current_source_location();
+        location.column -= name.len() as u32 + 1;
+        let prelude_start = input.position();
+
+        match_ignore_ascii_case! { &name,
+            "import" => {
+                let url = input.expect_url_or_string()?.as_ref().to_owned();
+                let media = MediaList::parse(input);
+                if self.seen_rules {
+                    let source = format!("@import {}", input.slice_from(prelude_start).trim());
+                    let diagnostic = Diagnostic::new(location, None, source, Reason::MisplacedImport);
+                    return Err(location.new_custom_error(diagnostic));
+                }
+                Ok(AtRulePrelude::Import(url, media))
+            },
+            "media" => Ok(AtRulePrelude::Media(MediaList::parse(input))),
+            _ => {
+                let source = format!("@{name}");
+                let diagnostic = Diagnostic::new(location, None, source, Reason::UnsupportedAtRule);
+                Err(location.new_custom_error(diagnostic))
+            },
+        }
+    }
+
+    fn rule_without_block(
+        &mut self,
+        prelude: AtRulePrelude,
+        _start: &ParserState,
+    ) -> Result<StyleSheetItem, ()> {
+        match prelude {
+            AtRulePrelude::Import(url, media) => Ok(StyleSheetItem::Import(url, media)),
+            AtRulePrelude::Media(_) => Err(()),
+        }
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: AtRulePrelude,
+        _start: &ParserState,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<StyleSheetItem, ParseError<'i, Diagnostic>> {
+        let AtRulePrelude::Media(media) = prelude else {
+            return Err(input.new_error(BasicParseErrorKind::AtRuleBodyInvalid));
+        };
+        // Imports are not allowed inside of `@media`.
+        self.seen_rules = true;
+
+        let items: Vec<_> = StyleSheetParser::new(input, self).collect();
+        let mut rules = Vec::new();
+        for item in items {
+            match item {
+                Ok(StyleSheetItem::Rule(rule)) => rules.push(rule),
+                Ok(StyleSheetItem::Media(nested)) => rules.extend(nested),
+                // Rejected while parsing the prelude.
+                Ok(StyleSheetItem::Import(..)) => {}
+                Err((err, source)) => self.diagnostics.push(rule_diagnostic(err, source)),
+            }
+        }
+        restrict_media(&mut rules, &media);
+
+        Ok(StyleSheetItem::Media(rules))
+    }
+}
+
+/// Make `rules` apply only to devices matching `media`.
+fn restrict_media(rules: &mut [Rule], media: &MediaList) {
+    if media.is_empty() {
+        return;
+    }
+    for rule in rules {
+        rule.media.push(media.clone());
+    }
 }
 
-impl<'i> RuleBodyItemParser<'i, Declaration, CowRcStr<'i>> for DeclParser {
+/// Diagnostic for a rule or a declaration that failed to parse.
+fn rule_diagnostic(err: ParseError<Diagnostic>, source: &str) -> Diagnostic {
+    match err.kind {
+        ParseErrorKind::Custom(diagnostic) => diagnostic,
+        ParseErrorKind::Basic(_) => {
+            Diagnostic::new(err.location, None, source.trim(), Reason::InvalidSyntax)
+        }
+    }
+}
+
+impl RuleBodyItemParser<'_, Declaration, Diagnostic> for DeclParser {
     fn parse_qualified(&self) -> bool {
         false
     }
@@ -116,14 +299,15 @@ impl<'i> RuleBodyItemParser<'i, Declaration, CowRcStr<'i>> for DeclParser {
 
 impl<'i> QualifiedRuleParser<'i> for RuleParser {
     type Prelude = SelectorGroup;
-    type QualifiedRule = Rule;
-    type Error = ();
+    type QualifiedRule = StyleSheetItem;
+    type Error = Diagnostic;
 
     // Selectors parsing
     fn parse_prelude<'t>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<SelectorGroup, ParseError<'i, ()>> {
+    ) -> Result<SelectorGroup, ParseError<'i, Diagnostic>> {
+        let location = input.current_source_location();
         let start_pos = input.position();
         while let Ok(_tok) = input.next() {
             //
@@ -132,9 +316,9 @@ impl<'i> QualifiedRuleParser<'i> for RuleParser {
 
         let selectors = input.slice(start_pos..end_pos).trim();
 
-        SelectorGroup::parse(selectors).map_err(|_| ParseError {
-            kind: ParseErrorKind::Custom(()),
-            location: input.current_source_location(),
+        SelectorGroup::parse(selectors).map_err(|_| {
+            let diagnostic = Diagnostic::new(location, None, selectors, Reason::InvalidSelector);
+            location.new_custom_error(diagnostic)
         })
     }
 
@@ -143,57 +327,302 @@ impl<'i> QualifiedRuleParser<'i> for RuleParser {
         prelude: SelectorGroup,
         _: &ParserState,
         input: &mut Parser<'i, 't>,
-    ) -> Result<Rule, ParseError<'i, ()>> {
-        let mut important_props = Props::new();
-        let mut props = Props::new();
+    ) -> Result<StyleSheetItem, ParseError<'i, Diagnostic>> {
+        self.seen_rules = true;
+        let (props, important_props) = parse_declaration_list(input, &mut self.diagnostics);
 
-        for item in RuleBodyParser::new(input, &mut DeclParser) {
-            match item {
-                Ok(decl) => unsafe {
-                    if decl.important {
-                        important_props.set_idx(decl.idx, decl.value);
-                    } else {
-                        props.set_idx(decl.idx, decl.value);
-                    }
-                },
-                Err(err) => {
-                    error!("Error parsing declaration: {:#?}", err);
+        Ok(StyleSheetItem::Rule(Rule {
+            selectors: prelude,
+            declarations: props,
+            important_declarations: important_props,
+            media: Vec::new(),
+        }))
+    }
+}
+
+/// Parse declarations up to the end of `input`, returning normal and important ones separately.
+fn parse_declaration_list(input: &mut Parser, diagnostics: &mut Vec<Diagnostic>) -> (Props, Props) {
+    let mut important_props = Props::new();
+    let mut props = Props::new();
+
+    let mut decl_parser = DeclParser::default();
+    for item in RuleBodyParser::new(input, &mut decl_parser) {
+        match item {
+            Ok(decl) => {
+                let props = if decl.important {
+                    &mut important_props
+                } else {
+                    &mut props
+                };
+                for (idx, value) in decl.longhands {
+                    unsafe { props.set_idx(idx, value) };
                 }
             }
+            Err((err, source)) => diagnostics.push(rule_diagnostic(err, source)),
         }
+    }
+    diagnostics.append(&mut decl_parser.diagnostics);
 
-        Ok(Rule {
-            selectors: prelude,
-            declarations: props,
-            important_declarations: important_props,
-        })
+    (props, important_props)
+}
+
+/// Rules of a style sheet in source order, indexed for matching.
+#[derive(Debug, Clone, Default)]
+pub struct StyleSheet {
+    rules: Vec<Rule>,
+    index: RuleIndex,
+}
+
+impl StyleSheet {
+    /// Create new empty style sheet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rules that may match `element` or its pseudo-elements, in source order. Other rules
+    /// certainly do not match it.
+    pub fn candidates<'a>(&'a self, element: &Element) -> impl Iterator<Item = &'a Rule> {
+        self.index
+            .candidates(element)
+            .into_iter()
+            .map(|position| &self.rules[position])
+    }
+}
+
+impl From<Vec<Rule>> for StyleSheet {
+    fn from(rules: Vec<Rule>) -> Self {
+        let index = RuleIndex::new(&rules);
+        Self { rules, index }
+    }
+}
+
+impl FromIterator<Rule> for StyleSheet {
+    fn from_iter<I: IntoIterator<Item = Rule>>(iter: I) -> Self {
+        Vec::from_iter(iter).into()
+    }
+}
+
+impl Deref for StyleSheet {
+    type Target = [Rule];
+
+    fn deref(&self) -> &[Rule] {
+        &self.rules
     }
 }
 
-pub type StyleSheet = Vec<Rule>;
+impl<'a> IntoIterator for &'a StyleSheet {
+    type Item = &'a Rule;
+    type IntoIter = std::slice::Iter<'a, Rule>;
 
-pub fn parse_stylesheet(css: &str) -> StyleSheet {
+    fn into_iter(self) -> Self::IntoIter {
+        self.rules.iter()
+    }
+}
+
+/// Parse top level items of a style sheet, diagnostics are sorted by their position.
+fn parse_items(css: &str) -> (Vec<StyleSheetItem>, Vec<Diagnostic>) {
     let mut input = ParserInput::new(css);
     let mut parser = Parser::new(&mut input);
-    let mut rule_parser = RuleParser;
+    let mut rule_parser = RuleParser::default();
+
+    let mut items = Vec::new();
+    let mut diagnostics = Vec::new();
+    for item in StyleSheetParser::new(&mut parser, &mut rule_parser) {
+        match item {
+            Ok(item) => items.push(item),
+            Err((err, source)) => diagnostics.push(rule_diagnostic(err, source)),
+        }
+    }
+    diagnostics.append(&mut rule_parser.diagnostics);
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+
+    (items, diagnostics)
+}
+
+/// Parse a self-contained style sheet, e.g. the default one. `@import` rules are ignored.
+///
+/// Invalid rules and declarations are skipped, a diagnostic is returned for each of them.
+pub fn parse_stylesheet(css: &str) -> (StyleSheet, Vec<Diagnostic>) {
+    let (items, diagnostics) = parse_items(css);
+    let stylesheet = items
+        .into_iter()
+        .flat_map(|item| match item {
+            StyleSheetItem::Rule(rule) => vec![rule],
+            StyleSheetItem::Media(rules) => rules,
+            StyleSheetItem::Import(url, _) => {
+                debug!("Ignoring import of {url}");
+                vec![]
+            }
+        })
+        .collect();
+
+    (stylesheet, diagnostics)
+}
+
+/// Loads style sheets together with style sheets they `@import`, recursively.
+struct ImportLoader<'a> {
+    loader: &'a dyn ResourceLoader,
+    /// Style sheets being imported, from the outermost one. Used to break import cycles.
+    importing: Vec<Url>,
+    /// Diagnostics of every parsed style sheet.
+    diagnostics: Vec<DocumentDiagnostic>,
+}
+
+impl ImportLoader<'_> {
+    /// Parse `css` coming from `source` and append its rules to `rules`, preceded by rules of its
+    /// imports in order.
+    fn parse(
+        &mut self,
+        css: &str,
+        source: StyleSource,
+        base_url: Option<&Url>,
+        rules: &mut Vec<Rule>,
+    ) {
+        let (items, diagnostics) = parse_items(css);
+        self.diagnostics.extend(
+            diagnostics
+                .into_iter()
+                .map(|diagnostic| DocumentDiagnostic {
+                    source: source.clone(),
+                    diagnostic,
+                }),
+        );
+
+        for item in items {
+            match item {
+                StyleSheetItem::Import(href, media) => match resolve_url(base_url, &href) {
+                    Some(url) => self.import(url, &media, rules),
+                    None => warn!("Cannot resolve URL {href:?} of imported style sheet"),
+                },
+                StyleSheetItem::Rule(rule) => rules.push(rule),
+                StyleSheetItem::Media(media_rules) => rules.extend(media_rules),
+            }
+        }
+    }
+
+    /// Load style sheet at `url` and append its rules, restricted to `media`, to `rules`.
+    fn import(&mut self, url: Url, media: &MediaList, rules: &mut Vec<Rule>) {
+        if self.importing.contains(&url) {
+            warn!("Style sheet {url} is imported in a cycle, ignoring the import");
+            return;
+        }
+
+        match self.loader.load(&url) {
+            Ok(css) => {
+                let start = rules.len();
+                self.importing.push(url.clone());
+                self.parse(&css, StyleSource::Url(url.clone()), Some(&url), rules);
+                self.importing.pop();
+                restrict_media(&mut rules[start..], media);
+            }
+            Err(err) => warn!("Cannot load style sheet {url}: {err}"),
+        }
+    }
+}
+
+/// Resolve possibly relative `href` against `base_url`.
+fn resolve_url(base_url: Option<&Url>, href: &str) -> Option<Url> {
+    match base_url {
+        Some(base_url) => base_url.join(href).ok(),
+        None => Url::parse(href).ok(),
+    }
+}
 
-    StyleSheetParser::new(&mut parser, &mut rule_parser)
-        .filter_map(Result::ok)
-        .collect()
+/// Parse value of a `style` attribute, which is a declaration list without braces.
+///
+/// Invalid declarations are skipped, a diagnostic is returned for each of them.
+pub fn parse_inline_style(css: &str) -> (InlineStyle, Vec<Diagnostic>) {
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    let mut diagnostics = Vec::new();
+    let (declarations, important_declarations) =
+        parse_declaration_list(&mut parser, &mut diagnostics);
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+
+    let inline_style = InlineStyle {
+        important_declarations,
+        declarations,
+    };
+    (inline_style, diagnostics)
 }
 
-pub fn from_style(html: &Html) -> StyleSheet {
-    let style_selector = crate::selector::SelectorGroup::parse("style").unwrap();
+/// Collect author style sheets of the document from `<style>` and `<link rel="stylesheet">`
+/// elements in document order.
+///
+/// External and imported style sheets are fetched with `loader`, relative URLs are resolved against
+/// `document_url`. Rules of style sheets with a `media` attribute apply only to matching devices.
+///
+/// Invalid rules and declarations are skipped, a diagnostic is returned for each of them, including
+/// the ones in `style` attributes.
+pub fn from_document(
+    html: &Html,
+    document_url: Option<&Url>,
+    loader: &dyn ResourceLoader,
+) -> (StyleSheet, Vec<DocumentDiagnostic>) {
+    let style_selector = SelectorGroup::parse("style, link").unwrap();
+
+    let mut rules = Vec::new();
+    let mut imports = ImportLoader {
+        loader,
+        importing: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+
+    for element in html.select(&style_selector) {
+        let media = MediaList::parse_str(element.attr("media").unwrap_or_default());
+
+        if element.value().name() == "style" {
+            let style_text = element.text().next().unwrap_or_default();
+            let start = rules.len();
+            let source = StyleSource::StyleElement(element.id());
+            imports.parse(style_text, source, document_url, &mut rules);
+            restrict_media(&mut rules[start..], &media);
+            continue;
+        }
 
-    let mut stylesheet = StyleSheet::new();
+        if !is_stylesheet_link(element.attr("rel").unwrap_or_default()) {
+            continue;
+        }
+        let Some(href) = element.attr("href").filter(|href| !href.is_empty()) else {
+            continue;
+        };
+        let Some(url) = resolve_url(document_url, href) else {
+            warn!("Cannot resolve URL {href:?} of linked style sheet");
+            continue;
+        };
+        imports.import(url, &media, &mut rules);
+    }
 
-    for style_block in html.select(&style_selector) {
-        let style_text = style_block.text().next().unwrap_or_default();
-        let block_stylesheet = parse_stylesheet(style_text);
-        stylesheet.extend(block_stylesheet);
+    // Inline styles are parsed again on every styling, they are only checked here.
+    let mut diagnostics = imports.diagnostics;
+    let inline_style_selector = SelectorGroup::parse("[style]").unwrap();
+    for element in html.select(&inline_style_selector) {
+        let (_, inline_diagnostics) = parse_inline_style(element.attr("style").unwrap_or_default());
+        let source = StyleSource::StyleAttribute(element.id());
+        diagnostics.extend(
+            inline_diagnostics
+                .into_iter()
+                .map(|diagnostic| DocumentDiagnostic {
+                    source: source.clone(),
+                    diagnostic,
+                }),
+        );
     }
+    for diagnostic in &diagnostics {
+        warn!("{diagnostic}");
+    }
+
+    (rules.into(), diagnostics)
+}
 
-    stylesheet
+/// Whether `rel` attribute of a `<link>` refers to a persistent style sheet. Alternative style
+/// sheets are not applied.
+fn is_stylesheet_link(rel: &str) -> bool {
+    let mut keywords = rel.split_ascii_whitespace();
+    keywords
+        .clone()
+        .any(|keyword| keyword.eq_ignore_ascii_case("stylesheet"))
+        && !keywords.any(|keyword| keyword.eq_ignore_ascii_case("alternate"))
 }
 
 // #[test]
@@ -232,3 +661,237 @@ pub fn from_style(html: &Html) -> StyleSheet {
 //         // println!("{:#?}", rule);
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::loader::LoadError;
+
+    /// Loader serving style sheets from memory.
+    #[derive(Debug, Default)]
+    struct MemoryLoader(HashMap<&'static str, &'static str>);
+
+    impl ResourceLoader for MemoryLoader {
+        fn load(&self, url: &Url) -> Result<String, LoadError> {
+            self.0
+                .get(url.as_str())
+                .map(|css| css.to_string())
+                .ok_or_else(|| LoadError::Io(std::io::ErrorKind::NotFound.into()))
+        }
+    }
+
+    /// Selectors of the style sheet rules in order.
+    fn selectors(stylesheet: &StyleSheet) -> Vec<String> {
+        stylesheet
+            .iter()
+            .map(|rule| rule.selectors.to_css_string())
+            .collect()
+    }
+
+    fn document_stylesheet(source: &str, loader: &MemoryLoader) -> StyleSheet {
+        let html = Html::parse_fragment(source);
+        let url = Url::parse("file:///site/index.html").unwrap();
+        from_document(&html, Some(&url), loader).0
+    }
+
+    #[test]
+    fn stylesheets_cascade_in_document_order() {
+        let loader = MemoryLoader(HashMap::from([
+            ("file:///site/css/a.css", "@import 'b.css'; .a {}"),
+            ("file:///site/css/b.css", ".b {}"),
+            ("file:///site/c.css", ".c {}"),
+        ]));
+        let source = r#"<style>@import url(c.css); .style1 {}</style>
+            <link rel="stylesheet" href="css/a.css">
+            <link rel="alternate stylesheet" href="c.css">
+            <link rel="icon" href="c.css">
+            <style>.style2 {}</style>"#;
+
+        assert_eq!(
+            selectors(&document_stylesheet(source, &loader)),
+            [".c", ".style1", ".b", ".a", ".style2"]
+        );
+    }
+
+    #[test]
+    fn import_cycles_and_late_imports_are_ignored() {
+        let loader = MemoryLoader(HashMap::from([
+            (
+                "file:///site/a.css",
+                "@import 'b.css'; .a {} @import 'c.css';",
+            ),
+            (
+                "file:///site/b.css",
+                "@import 'a.css'; @import 'missing.css'; .b {}",
+            ),
+            ("file:///site/c.css", ".c {}"),
+        ]));
+        let source = r#"<link rel="StyleSheet" href="a.css">"#;
+
+        assert_eq!(
+            selectors(&document_stylesheet(source, &loader)),
+            [".b", ".a"]
+        );
+    }
+
+    #[test]
+    fn media_lists_restrict_rules() {
+        let loader = MemoryLoader(HashMap::from([
+            ("file:///site/print.css", ".print {}"),
+            ("file:///site/wide.css", ".wide {}"),
+        ]));
+        let source = r#"<style>
+                @import "print.css" print;
+                @media screen { .screen {} @media (min-width: 600px) { .nested {} } }
+                .all {}
+            </style>
+            <link rel="stylesheet" href="wide.css" media="(min-width: 600px)">"#;
+        let stylesheet = document_stylesheet(source, &loader);
+        let applied = |width| {
+            let device = Device {
+                width,
+                ..Default::default()
+            };
+            stylesheet
+                .iter()
+                .filter(|rule| rule.matches_media(&device))
+                .map(|rule| rule.selectors.to_css_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(applied(400.0), [".screen", ".all"]);
+        assert_eq!(applied(800.0), [".screen", ".nested", ".all", ".wide"]);
+    }
+
+    #[test]
+    fn invalid_parts_are_reported_with_positions() {
+        let css = "p { color: red; colr: blue; width: red; }\n\
+                   a:::b { color: red }\n\
+                   @font-face { font-family: x }\n\
+                   div { color red }\n\
+                   @import 'late.css';";
+        let (stylesheet, diagnostics) = parse_stylesheet(css);
+
+        assert_eq!(selectors(&stylesheet), ["p", "div"]);
+        let diagnostics: Vec<_> = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.line,
+                    d.column,
+                    d.property.as_deref(),
+                    d.value.as_str(),
+                    d.reason.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                (1, 17, Some("colr"), "blue", Reason::UnknownProperty),
+                (1, 36, Some("width"), "red", Reason::InvalidValue),
+                (2, 1, None, "a:::b", Reason::InvalidSelector),
+                (3, 1, None, "@font-face", Reason::UnsupportedAtRule),
+                (4, 12, None, "color red", Reason::InvalidSyntax),
+                (5, 1, None, "@import 'late.css'", Reason::MisplacedImport),
+            ]
+        );
+    }
+
+    #[test]
+    fn function_values_are_parsed_whole() {
+        use crate::css::props::Color;
+
+        let css = "p { color: rgb(255, 0, 0) !important; width: rgb(1, 2, 3) }";
+        let (stylesheet, diagnostics) = parse_stylesheet(css);
+
+        assert_eq!(
+            stylesheet[0].important_declarations.get::<Color>(),
+            Some(&Color::Color(values::Color(255, 0, 0)))
+        );
+        let values: Vec<_> = diagnostics.iter().map(|d| d.value.as_str()).collect();
+        assert_eq!(values, ["rgb(1, 2, 3)"]);
+    }
+
+    #[test]
+    fn unsupported_values_fall_back_to_initial_values() {
+        use crate::css::props::{Display, MarginTop};
+
+        let css = "p { margin: 1px 2px 3px 4px 5px; display: table }
+            q::before { content: open-quote }";
+        let (stylesheet, diagnostics) = parse_stylesheet(css);
+
+        let reasons: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.property.as_deref(), d.reason.clone()))
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                (Some("margin"), Reason::InvalidValue),
+                (Some("display"), Reason::UnsupportedValue),
+                (Some("content"), Reason::UnsupportedValue),
+            ]
+        );
+        let declarations = &stylesheet[0].declarations;
+        assert_eq!(declarations.get::<MarginTop>(), None);
+        assert_eq!(declarations.get::<Display>(), Some(&Display::Inline));
+    }
+
+    #[test]
+    fn document_diagnostics_keep_their_source() {
+        let loader = MemoryLoader(HashMap::from([(
+            "file:///site/a.css",
+            "p { display: table }",
+        )]));
+        let html = Html::parse_fragment(
+            r#"<style>@import 'a.css'; p { colour: red }</style>
+            <p style="color: red; display: table-cell">x</p>"#,
+        );
+        let url = Url::parse("file:///site/index.html").unwrap();
+        let (_, diagnostics) = from_document(&html, Some(&url), &loader);
+
+        let style = html
+            .select(&SelectorGroup::parse("style").unwrap())
+            .next()
+            .unwrap()
+            .id();
+        let p = html
+            .select(&SelectorGroup::parse("p").unwrap())
+            .next()
+            .unwrap()
+            .id();
+        let sources: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.source.clone(), d.diagnostic.reason.clone()))
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                (StyleSource::StyleElement(style), Reason::UnknownProperty),
+                (
+                    StyleSource::Url(Url::parse("file:///site/a.css").unwrap()),
+                    Reason::UnsupportedValue
+                ),
+                (StyleSource::StyleAttribute(p), Reason::UnsupportedValue),
+            ]
+        );
+        assert_eq!(
+            diagnostics[2].to_string(),
+            "style attribute:1:22: unsupported value `display: table-cell`"
+        );
+    }
+
+    #[test]
+    fn default_stylesheet_is_valid() {
+        let (_, diagnostics) = parse_stylesheet(include_str!("default.css"));
+        // Values the engine does not support yet, e.g. `display: list-item`, are still valid.
+        let invalid: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.reason != Reason::UnsupportedValue)
+            .collect();
+        assert_eq!(invalid, Vec::<&Diagnostic>::new());
+    }
+}
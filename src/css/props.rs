@@ -1,6 +1,6 @@
 // We want to be as safe as possible, so forbid implementing Indexable and access to PropUnion.
 #![allow(private_bounds)]
-use std::{collections::HashMap, fmt};
+use std::{collections::HashMap, fmt, sync::LazyLock};
 
 use css_macros::css_properties;
 use cssparser::Parser;
@@ -14,6 +14,31 @@ pub trait ParseableProperty<'i>: Sized {
     fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ()>;
 }
 
+/// Turn lengths inside of a value into lengths in px, as computed values have them.
+///
+/// Implemented by generated property types and values, the default implementation is for values
+/// without lengths.
+pub trait ResolveLengths {
+    /// `font_size` is the computed font size of the element in px, `em` and `ex` are relative to it.
+    fn resolve_lengths(&mut self, _font_size: f32) {}
+}
+
+impl<T: ResolveLengths> ResolveLengths for Vec<T> {
+    fn resolve_lengths(&mut self, font_size: f32) {
+        for value in self {
+            value.resolve_lengths(font_size);
+        }
+    }
+}
+
+impl<T: ResolveLengths> ResolveLengths for Option<T> {
+    fn resolve_lengths(&mut self, font_size: f32) {
+        if let Some(value) = self {
+            value.resolve_lengths(font_size);
+        }
+    }
+}
+
 pub type PropIndex = u8;
 
 /// Ad-hoc trait needed for efficient representation in memory
@@ -23,42 +48,171 @@ pub type PropIndex = u8;
 #[sealed]
 pub trait Property {
     const ID: PropIndex;
+    /// Name of the property as written in style sheets.
+    const NAME: &'static str;
+    /// Whether the property is inherited by default.
+    const INHERITED: bool;
 }
 
 css_properties!("src/css/props.json");
 
+/// Initial values of properties, parsed from `props.json` once.
+static INITIAL_VALUES: LazyLock<Props> = LazyLock::new(Props::initial_values);
+
+/// Parser of a longhand value, paired with index of the longhand.
+type LonghandParser = (
+    PropIndex,
+    for<'i, 't> fn(&mut Parser<'i, 't>) -> Result<PropUnion, ()>,
+);
+
+/// Expand value of a shorthand for the four sides of a box, e.g. `margin`, into its `top`,
+/// `right`, `bottom` and `left` longhands.
+///
+/// As CSS 2.1 defines, one value applies to all sides, two values are for vertical and horizontal
+/// sides, three are for top, horizontal and bottom sides and four go clockwise from the top.
+fn expand_box_sides(
+    input: &mut Parser,
+    sides: &[LonghandParser; 4],
+) -> Result<Vec<(PropIndex, PropUnion)>, ()> {
+    // All sides share the grammar, so values are delimited by the parser of the top one.
+    let mut starts = Vec::new();
+    while !input.is_exhausted() {
+        starts.push(input.state());
+        (sides[0].1)(input)?;
+    }
+    let value_of_side = match starts.len() {
+        1 => [0, 0, 0, 0],
+        2 => [0, 1, 0, 1],
+        3 => [0, 1, 2, 1],
+        4 => [0, 1, 2, 3],
+        _ => return Err(()),
+    };
+
+    sides
+        .iter()
+        .zip(value_of_side)
+        .map(|(&(idx, parse), value)| {
+            input.reset(&starts[value]);
+            parse(input).map(|value| (idx, value))
+        })
+        .collect()
+}
+
+/// Expand value of a shorthand whose parts can go in any order, e.g. `background`. Omitted
+/// longhands are reset to their initial values.
+fn expand_any_order(
+    input: &mut Parser,
+    longhands: &[LonghandParser],
+) -> Result<Vec<(PropIndex, PropUnion)>, ()> {
+    let start = input.state();
+    if input
+        .try_parse(|input| input.expect_ident_matching("inherit"))
+        .is_ok()
+    {
+        return longhands
+            .iter()
+            .map(|&(idx, parse)| {
+                input.reset(&start);
+                parse(input).map(|value| (idx, value))
+            })
+            .collect();
+    }
+
+    let mut values: Vec<Option<PropUnion>> = longhands.iter().map(|_| None).collect();
+    while !input.is_exhausted() {
+        let (i, value) = longhands
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| values[i].is_none())
+            .find_map(|(i, &(_, parse))| input.try_parse(parse).ok().map(|value| (i, value)))
+            .ok_or(())?;
+        values[i] = Some(value);
+    }
+
+    Ok(longhands
+        .iter()
+        .zip(values)
+        .filter_map(|(&(idx, _), value)| Some((idx, value.or_else(|| initial_value(idx))?)))
+        .collect())
+}
+
+/// Initial value of property `idx`, shorthands have none.
+pub(crate) fn initial_value(idx: PropIndex) -> Option<PropUnion> {
+    INITIAL_VALUES
+        .0
+        .get(&idx)
+        .map(|value| unsafe { value.clone_variant(idx) })
+}
+
+/// Whether the engine can use a valid `value` of property `idx`.
+///
+/// Unsupported values are replaced by the initial value while parsing, so that style and layout
+/// never see them.
+pub(crate) fn is_supported(idx: PropIndex, value: &PropUnion) -> bool {
+    match idx {
+        Display::ID => matches!(
+            <&Display>::from(value),
+            Display::Inline | Display::Block | Display::None | Display::Inherit
+        ),
+        // Images and quotes are not generated yet.
+        Content::ID => match <&Content>::from(value) {
+            Content::ContentList(values::ContentList(items)) => items.iter().all(|item| {
+                !matches!(
+                    item,
+                    values::ContentItem::Uri(_)
+                        | values::ContentItem::OpenQuote
+                        | values::ContentItem::CloseQuote
+                        | values::ContentItem::NoOpenQuote
+                        | values::ContentItem::NoCloseQuote
+                )
+            }),
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+// Conversions below work with computed values, where `inherit` is already replaced by the value of
+// the parent (see `Props::compute`).
+
+/// Initial value of property `T` to use for `inherit`, which never gets here by construction.
+fn not_computed<T: Property, V>(initial: V) -> V {
+    debug_assert!(false, "`{}: inherit` is not computed", T::NAME);
+    initial
+}
+
 impl MarginTop {
-    pub fn to_px(&self) -> f32 {
+    pub fn to_px(&self, containing_block_width: f32) -> f32 {
         match self {
-            MarginTop::Inherit => todo!(),
-            MarginTop::MarginWidth(margin_width) => margin_width.to_px(),
+            MarginTop::Inherit => not_computed::<Self, _>(0.0),
+            MarginTop::MarginWidth(margin_width) => margin_width.to_px(containing_block_width),
         }
     }
 }
 
 impl MarginRight {
-    pub fn to_px(&self) -> f32 {
+    pub fn to_px(&self, containing_block_width: f32) -> f32 {
         match self {
-            MarginRight::Inherit => todo!(),
-            MarginRight::MarginWidth(margin_width) => margin_width.to_px(),
+            MarginRight::Inherit => not_computed::<Self, _>(0.0),
+            MarginRight::MarginWidth(margin_width) => margin_width.to_px(containing_block_width),
         }
     }
 }
 
 impl MarginBottom {
-    pub fn to_px(&self) -> f32 {
+    pub fn to_px(&self, containing_block_width: f32) -> f32 {
         match self {
-            MarginBottom::Inherit => todo!(),
-            MarginBottom::MarginWidth(margin_width) => margin_width.to_px(),
+            MarginBottom::Inherit => not_computed::<Self, _>(0.0),
+            MarginBottom::MarginWidth(margin_width) => margin_width.to_px(containing_block_width),
         }
     }
 }
 
 impl MarginLeft {
-    pub fn to_px(&self) -> f32 {
+    pub fn to_px(&self, containing_block_width: f32) -> f32 {
         match self {
-            MarginLeft::Inherit => todo!(),
-            MarginLeft::MarginWidth(margin_width) => margin_width.to_px(),
+            MarginLeft::Inherit => not_computed::<Self, _>(0.0),
+            MarginLeft::MarginWidth(margin_width) => margin_width.to_px(containing_block_width),
         }
     }
 }
@@ -66,7 +220,7 @@ impl MarginLeft {
 impl BorderTopWidth {
     pub fn to_px(&self) -> f32 {
         match self {
-            BorderTopWidth::Inherit => todo!(),
+            BorderTopWidth::Inherit => not_computed::<Self, _>(0.0),
             BorderTopWidth::BorderWidth(border_width) => border_width.to_px(),
         }
     }
@@ -75,7 +229,7 @@ impl BorderTopWidth {
 impl BorderRightWidth {
     pub fn to_px(&self) -> f32 {
         match self {
-            BorderRightWidth::Inherit => todo!(),
+            BorderRightWidth::Inherit => not_computed::<Self, _>(0.0),
             BorderRightWidth::BorderWidth(border_width) => border_width.to_px(),
         }
     }
@@ -84,7 +238,7 @@ impl BorderRightWidth {
 impl BorderBottomWidth {
     pub fn to_px(&self) -> f32 {
         match self {
-            BorderBottomWidth::Inherit => todo!(),
+            BorderBottomWidth::Inherit => not_computed::<Self, _>(0.0),
             BorderBottomWidth::BorderWidth(border_width) => border_width.to_px(),
         }
     }
@@ -93,94 +247,149 @@ impl BorderBottomWidth {
 impl BorderLeftWidth {
     pub fn to_px(&self) -> f32 {
         match self {
-            BorderLeftWidth::Inherit => todo!(),
+            BorderLeftWidth::Inherit => not_computed::<Self, _>(0.0),
             BorderLeftWidth::BorderWidth(border_width) => border_width.to_px(),
         }
     }
 }
 
 impl PaddingTop {
-    pub fn to_px(&self) -> f32 {
+    pub fn to_px(&self, containing_block_width: f32) -> f32 {
         match self {
-            PaddingTop::Inherit => todo!(),
-            PaddingTop::PaddingWidth(padding_width) => padding_width.to_px(),
+            PaddingTop::Inherit => not_computed::<Self, _>(0.0),
+            PaddingTop::PaddingWidth(padding_width) => padding_width.to_px(containing_block_width),
         }
     }
 }
 
 impl PaddingRight {
-    pub fn to_px(&self) -> f32 {
+    pub fn to_px(&self, containing_block_width: f32) -> f32 {
         match self {
-            PaddingRight::Inherit => todo!(),
-            PaddingRight::PaddingWidth(padding_width) => padding_width.to_px(),
+            PaddingRight::Inherit => not_computed::<Self, _>(0.0),
+            PaddingRight::PaddingWidth(padding_width) => {
+                padding_width.to_px(containing_block_width)
+            }
         }
     }
 }
 
 impl PaddingBottom {
-    pub fn to_px(&self) -> f32 {
+    pub fn to_px(&self, containing_block_width: f32) -> f32 {
         match self {
-            PaddingBottom::Inherit => todo!(),
-            PaddingBottom::PaddingWidth(padding_width) => padding_width.to_px(),
+            PaddingBottom::Inherit => not_computed::<Self, _>(0.0),
+            PaddingBottom::PaddingWidth(padding_width) => {
+                padding_width.to_px(containing_block_width)
+            }
         }
     }
 }
 
 impl PaddingLeft {
-    pub fn to_px(&self) -> f32 {
+    pub fn to_px(&self, containing_block_width: f32) -> f32 {
         match self {
-            PaddingLeft::Inherit => todo!(),
-            PaddingLeft::PaddingWidth(padding_width) => padding_width.to_px(),
+            PaddingLeft::Inherit => not_computed::<Self, _>(0.0),
+            PaddingLeft::PaddingWidth(padding_width) => padding_width.to_px(containing_block_width),
         }
     }
 }
 
 impl Width {
-    pub fn to_px(&self) -> f32 {
+    pub fn to_px(&self, containing_block_width: f32) -> f32 {
         match self {
+            Width::Inherit => not_computed::<Self, _>(0.0),
             Width::Length(length) => length.to_px(),
+            Width::Percentage(percentage) => percentage.ratio() * containing_block_width,
             Width::Auto => {
                 warn!("Setting auto width to zero.");
                 0.0
             }
-            _ => todo!(),
         }
     }
 }
 
-impl From<BackgroundColor> for values::Color {
+impl Height {
+    /// Height in px, or `None` if it depends on the content.
+    ///
+    /// Percentages refer to the height of the containing block, and are treated as `auto` when
+    /// that height is not definite.
+    pub fn to_px(&self, containing_block_height: Option<f32>) -> Option<f32> {
+        match self {
+            Height::Inherit => not_computed::<Self, _>(None),
+            Height::Length(length) => Some(length.to_px()),
+            Height::Percentage(percentage) => {
+                containing_block_height.map(|height| percentage.ratio() * height)
+            }
+            Height::Auto => None,
+        }
+    }
+}
+
+impl FontSize {
+    /// Font size in px, relative sizes are resolved against `parent_font_size`.
+    pub fn to_px(&self, parent_font_size: f32) -> f32 {
+        match self {
+            FontSize::Inherit => not_computed::<Self, _>(parent_font_size),
+            FontSize::AbsoluteSize(size) => size.to_px(),
+            FontSize::RelativeSize(size) => size.to_px(parent_font_size),
+            FontSize::Length(length) => {
+                // `em` and `ex` of `font-size` itself refer to the font of the parent.
+                let mut length = *length;
+                length.resolve_lengths(parent_font_size);
+                length.to_px()
+            }
+            FontSize::Percentage(percentage) => percentage.ratio() * parent_font_size,
+        }
+    }
+}
+
+// Colors are `None` if they are transparent, so that nothing is painted.
+
+impl From<BackgroundColor> for Option<values::Color> {
     fn from(value: BackgroundColor) -> Self {
         match value {
-            BackgroundColor::Transparent => todo!(),
-            BackgroundColor::Inherit => todo!(),
-            BackgroundColor::Color(color) => color,
+            BackgroundColor::Transparent => None,
+            BackgroundColor::Inherit => not_computed::<BackgroundColor, _>(None),
+            BackgroundColor::Color(color) => Some(color),
+        }
+    }
+}
+
+impl From<BorderTopColor> for Option<values::Color> {
+    fn from(value: BorderTopColor) -> Self {
+        match value {
+            BorderTopColor::Transparent => None,
+            BorderTopColor::Inherit => not_computed::<BorderTopColor, _>(None),
+            BorderTopColor::Color(color) => Some(color),
+        }
+    }
+}
+
+impl From<BorderRightColor> for Option<values::Color> {
+    fn from(value: BorderRightColor) -> Self {
+        match value {
+            BorderRightColor::Transparent => None,
+            BorderRightColor::Inherit => not_computed::<BorderRightColor, _>(None),
+            BorderRightColor::Color(color) => Some(color),
         }
     }
 }
 
-impl From<Background> for values::Color {
-    fn from(value: Background) -> Self {
+impl From<BorderBottomColor> for Option<values::Color> {
+    fn from(value: BorderBottomColor) -> Self {
         match value {
-            Background::Inherit => todo!(),
-            Background::BackgroundV0(bg) => bg
-                .background_color
-                .map(|bg| bg.into())
-                .unwrap_or(values::Color(255, 255, 255)),
+            BorderBottomColor::Transparent => None,
+            BorderBottomColor::Inherit => not_computed::<BorderBottomColor, _>(None),
+            BorderBottomColor::Color(color) => Some(color),
         }
     }
 }
 
-impl From<BorderColor> for values::Color {
-    fn from(value: BorderColor) -> Self {
+impl From<BorderLeftColor> for Option<values::Color> {
+    fn from(value: BorderLeftColor) -> Self {
         match value {
-            BorderColor::Inherit => todo!(),
-            BorderColor::BorderColorV0(border_color_v0s) => match border_color_v0s.len() {
-                1 => match border_color_v0s.first() {
-                    Some(BorderColorV0::Color(c)) => *c,
-                    _ => todo!(),
-                },
-                _ => todo!(),
-            },
+            BorderLeftColor::Transparent => None,
+            BorderLeftColor::Inherit => not_computed::<BorderLeftColor, _>(None),
+            BorderLeftColor::Color(color) => Some(color),
         }
     }
 }
@@ -215,6 +424,59 @@ impl Props {
         self.0.insert(idx, value.into());
     }
 
+    /// Turn specified values into computed values.
+    ///
+    /// Resolves `inherit` keyword, takes values of inherited properties from the `parent` and sets
+    /// the remaining properties to their initial values. Finally all lengths, including
+    /// `font-size`, are converted to px.
+    pub fn compute(&mut self, parent: Option<&Props>) {
+        for idx in 0..PROPS_COUNT as PropIndex {
+            let specified = self.0.get(&idx);
+            let is_inherit = specified.is_some_and(|value| unsafe { value.is_inherit(idx) });
+            if specified.is_some() && !is_inherit {
+                continue;
+            }
+
+            let value = match parent.and_then(|parent| parent.0.get(&idx)) {
+                Some(value) if is_inherit || is_inherited(idx) => Some(value),
+                _ => INITIAL_VALUES.0.get(&idx),
+            };
+            match value {
+                Some(value) => unsafe { self.set_idx(idx, value.clone_variant(idx)) },
+                None => {
+                    self.0.remove(&idx);
+                }
+            }
+        }
+
+        let medium = values::AbsoluteSize::Medium.to_px();
+        let parent_font_size = parent
+            .and_then(|parent| parent.get::<FontSize>())
+            .map_or(medium, |font_size| font_size.to_px(medium));
+        let font_size = self
+            .get::<FontSize>()
+            .map_or(parent_font_size, |font_size| {
+                font_size.to_px(parent_font_size)
+            });
+        self.set(FontSize::Length(values::Length::Absolute(
+            values::Absolute::Px(font_size),
+        )));
+
+        for (&idx, value) in self.0.iter_mut() {
+            unsafe { value.resolve_lengths(idx, font_size) };
+        }
+    }
+
+    /// Whether no property is set.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Keep only properties for which `f` returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(PropIndex) -> bool) {
+        self.0.retain(|&idx, _| f(idx));
+    }
+
     /// Overwrite properties from key value pairs of `other` props.
     pub fn extend(&mut self, other: &Props) {
         for (&k, v) in other.0.iter() {
@@ -242,3 +504,182 @@ impl Props {
 //     //     }
 //     // };
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initial_values_parse() {
+        let initial_values = Props::initial_values();
+        assert_eq!(initial_values.get::<Display>(), Some(&Display::Inline));
+        assert_eq!(initial_values.get::<Margin>(), None);
+    }
+
+    #[test]
+    fn inherited_properties_come_from_parent() {
+        let mut parent = Props::new();
+        parent.set(Color::Color(values::Color(255, 0, 0)));
+        parent.set(Display::Block);
+        parent.compute(None);
+
+        let mut child = Props::new();
+        child.compute(Some(&parent));
+
+        assert_eq!(
+            child.get::<Color>(),
+            Some(&Color::Color(values::Color(255, 0, 0)))
+        );
+        assert_eq!(child.get::<Display>(), Some(&Display::Inline));
+    }
+
+    #[test]
+    fn inherit_keyword_takes_parent_value() {
+        let mut parent = Props::new();
+        parent.set(Display::Block);
+        parent.compute(None);
+
+        let mut child = Props::new();
+        child.set(Display::Inherit);
+        child.compute(Some(&parent));
+        assert_eq!(child.get::<Display>(), Some(&Display::Block));
+
+        let mut root = Props::new();
+        root.set(Display::Inherit);
+        root.compute(None);
+        assert_eq!(root.get::<Display>(), Some(&Display::Inline));
+    }
+
+    #[test]
+    fn box_shorthands_expand_into_sides() {
+        let margins = |value| {
+            let props = parse_props(&[("margin", value)]);
+            assert_eq!(props.get::<Margin>(), None);
+            [
+                props.get::<MarginTop>().unwrap().to_px(0.0),
+                props.get::<MarginRight>().unwrap().to_px(0.0),
+                props.get::<MarginBottom>().unwrap().to_px(0.0),
+                props.get::<MarginLeft>().unwrap().to_px(0.0),
+            ]
+        };
+        assert_eq!(margins("1px"), [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(margins("1px 2px"), [1.0, 2.0, 1.0, 2.0]);
+        assert_eq!(margins("1px 2px 3px"), [1.0, 2.0, 3.0, 2.0]);
+        assert_eq!(margins("1px 2px 3px 4px"), [1.0, 2.0, 3.0, 4.0]);
+
+        let props = parse_props(&[("border-color", "red transparent")]);
+        let red = values::Color(255, 0, 0);
+        assert_eq!(
+            props.get::<BorderTopColor>(),
+            Some(&BorderTopColor::Color(red))
+        );
+        assert_eq!(
+            props.get::<BorderLeftColor>(),
+            Some(&BorderLeftColor::Transparent)
+        );
+
+        let props = parse_props(&[("padding", "inherit")]);
+        assert_eq!(props.get::<PaddingBottom>(), Some(&PaddingBottom::Inherit));
+    }
+
+    #[test]
+    fn omitted_longhands_are_reset_to_initial_values() {
+        let props = parse_props(&[("background", "repeat-x red")]);
+        assert_eq!(
+            props.get::<BackgroundColor>(),
+            Some(&BackgroundColor::Color(values::Color(255, 0, 0)))
+        );
+        assert_eq!(
+            props.get::<BackgroundRepeat>(),
+            Some(&BackgroundRepeat::RepeatX)
+        );
+        assert_eq!(props.get::<BackgroundImage>(), Some(&BackgroundImage::None));
+    }
+
+    #[test]
+    fn content_and_counters_parse() {
+        use values::{ContentItem, CounterStyle};
+
+        let props = parse_props(&[
+            (
+                "content",
+                r#""Chapter " counter(chapter, upper-roman) counters(s, ".") attr(title)"#,
+            ),
+            ("counter-reset", "chapter section 2"),
+            ("counter-increment", "none"),
+        ]);
+        assert_eq!(
+            props.get::<Content>(),
+            Some(&Content::ContentList(values::ContentList(vec![
+                ContentItem::String("Chapter ".to_owned()),
+                ContentItem::Counter("chapter".to_owned(), CounterStyle::UpperRoman),
+                ContentItem::Counters("s".to_owned(), ".".to_owned(), CounterStyle::Decimal),
+                ContentItem::Attr("title".to_owned()),
+            ])))
+        );
+        assert_eq!(
+            props.get::<CounterReset>(),
+            Some(&CounterReset::CounterList(values::CounterList(vec![
+                ("chapter".to_owned(), None),
+                ("section".to_owned(), Some(2)),
+            ])))
+        );
+        assert_eq!(
+            props.get::<CounterIncrement>(),
+            Some(&CounterIncrement::None)
+        );
+
+        for invalid in ["counter()", "attr(1)", "counters(s)", "\"a\" 1px"] {
+            let mut input = cssparser::ParserInput::new(invalid);
+            let mut parser = Parser::new(&mut input);
+            assert!(PropUnion::parse_declaration("content", &mut parser).is_err());
+        }
+
+        let styles = [
+            (CounterStyle::UpperRoman, 1994, "MCMXCIV"),
+            (CounterStyle::LowerAlpha, 28, "ab"),
+            (CounterStyle::LowerGreek, 2, "β"),
+            (CounterStyle::DecimalLeadingZero, -3, "-03"),
+            (CounterStyle::LowerRoman, 0, "0"),
+        ];
+        for (style, value, formatted) in styles {
+            assert_eq!(style.format(value), formatted);
+        }
+    }
+
+    fn parse_props(declarations: &[(&str, &str)]) -> Props {
+        let mut props = Props::new();
+        for (name, value) in declarations {
+            let mut input = cssparser::ParserInput::new(value);
+            let mut parser = Parser::new(&mut input);
+            for (idx, value) in PropUnion::parse_declaration(name, &mut parser).unwrap() {
+                unsafe { props.set_idx(idx, value) };
+            }
+        }
+        props
+    }
+
+    #[test]
+    fn lengths_are_computed_in_px() {
+        let mut parent = parse_props(&[("font-size", "12pt")]);
+        parent.compute(None);
+        assert_eq!(parent.get::<FontSize>().unwrap().to_px(0.0), 16.0);
+
+        let mut child = parse_props(&[
+            ("font-size", "2em"),
+            ("margin-top", "1em"),
+            ("padding-left", "0.5ex"),
+            ("border-width", "1in 1cm"),
+            ("width", "3pc"),
+        ]);
+        child.compute(Some(&parent));
+
+        assert_eq!(child.get::<FontSize>().unwrap().to_px(0.0), 32.0);
+        assert_eq!(child.get::<MarginTop>().unwrap().to_px(0.0), 32.0);
+        assert_eq!(child.get::<PaddingLeft>().unwrap().to_px(0.0), 8.0);
+        assert_eq!(child.get::<BorderTopWidth>().unwrap().to_px(), 96.0);
+        let border_right = child.get::<BorderRightWidth>().unwrap().to_px();
+        assert!((border_right - 96.0 / 2.54).abs() < 1e-4);
+        assert_eq!(child.get::<Width>().unwrap().to_px(0.0), 48.0);
+    }
+}
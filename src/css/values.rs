@@ -1,4 +1,4 @@
-use super::props::ParseableProperty;
+use super::props::{ParseableProperty, ResolveLengths};
 
 use cssparser::{
     color::{parse_hash_color, parse_named_color},
@@ -15,6 +15,12 @@ pub enum Relative {
     Ex(f32),
 }
 
+impl Relative {
+    /// Ratio between x-height and font size. Fonts are not known during style computation, so
+    /// the fallback value suggested by the specification is used for every font.
+    pub const EX_RATIO: f32 = 0.5;
+}
+
 /// Absolute length
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Absolute {
@@ -56,27 +62,69 @@ impl<'i> ParseableProperty<'i> for Length {
                     _ => Err(()),
                 }
             }
+            // Unit may be omitted for zero length.
+            cssparser::Token::Number { value, .. } if *value == 0.0 => {
+                Ok(Self::Absolute(Absolute::Px(0.0)))
+            }
             _ => Err(()),
         }
     }
 }
 
+impl Absolute {
+    /// Number of px in one inch, CSS reference pixel is 1/96 of an inch.
+    pub const PX_PER_IN: f32 = 96.0;
+
+    pub fn to_px(&self) -> f32 {
+        match self {
+            Absolute::In(inches) => inches * Self::PX_PER_IN,
+            Absolute::Cm(cm) => cm * Self::PX_PER_IN / 2.54,
+            Absolute::Mm(mm) => mm * Self::PX_PER_IN / 25.4,
+            Absolute::Pt(pt) => pt * Self::PX_PER_IN / 72.0,
+            Absolute::Pc(pc) => pc * Self::PX_PER_IN / 6.0,
+            Absolute::Px(px) => *px,
+        }
+    }
+}
+
 impl Length {
+    /// Length in px. Relative lengths are resolved during style computation, see
+    /// [`ResolveLengths`], so only absolute lengths get here.
     pub fn to_px(&self) -> f32 {
         match self {
-            Length::Relative(_) => todo!(),
-            Length::Absolute(absolute) => match absolute {
-                Absolute::Px(px) => *px,
-                _ => todo!(),
-            },
+            Length::Relative(_) => {
+                debug_assert!(false, "relative length is not resolved");
+                let mut length = *self;
+                length.resolve_lengths(AbsoluteSize::Medium.to_px());
+                length.to_px()
+            }
+            Length::Absolute(absolute) => absolute.to_px(),
         }
     }
 }
 
+impl ResolveLengths for Length {
+    fn resolve_lengths(&mut self, font_size: f32) {
+        let px = match self {
+            Length::Relative(Relative::Em(em)) => *em * font_size,
+            Length::Relative(Relative::Ex(ex)) => *ex * font_size * Relative::EX_RATIO,
+            Length::Absolute(absolute) => absolute.to_px(),
+        };
+        *self = Length::Absolute(Absolute::Px(px));
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 /// Contains normalized value in range from 0.0 to 1.0.
 pub struct Percentage(f32);
 
+impl Percentage {
+    /// Percentage as a fraction, e.g. `0.5` for `50%`.
+    pub fn ratio(&self) -> f32 {
+        self.0
+    }
+}
+
 impl<'i> ParseableProperty<'i> for Percentage {
     fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ()> {
         match input.next().map_err(|_| ())? {
@@ -106,10 +154,19 @@ impl<'i> ParseableProperty<'i> for PaddingWidth {
 }
 
 impl PaddingWidth {
-    pub fn to_px(&self) -> f32 {
+    /// Width in px, percentages refer to the width of the containing block.
+    pub fn to_px(&self, containing_block_width: f32) -> f32 {
         match self {
             PaddingWidth::Length(length) => length.to_px(),
-            _ => todo!(),
+            PaddingWidth::Percentage(percentage) => percentage.ratio() * containing_block_width,
+        }
+    }
+}
+
+impl ResolveLengths for PaddingWidth {
+    fn resolve_lengths(&mut self, font_size: f32) {
+        if let PaddingWidth::Length(length) = self {
+            length.resolve_lengths(font_size);
         }
     }
 }
@@ -141,10 +198,21 @@ impl<'i> ParseableProperty<'i> for MarginWidth {
 }
 
 impl MarginWidth {
-    pub fn to_px(&self) -> f32 {
+    /// Width in px, percentages refer to the width of the containing block. Used value of `auto`
+    /// depends on the layout, here it is treated as zero.
+    pub fn to_px(&self, containing_block_width: f32) -> f32 {
         match self {
             MarginWidth::Length(length) => length.to_px(),
-            _ => todo!(),
+            MarginWidth::Percentage(percentage) => percentage.ratio() * containing_block_width,
+            MarginWidth::Auto => 0.0,
+        }
+    }
+}
+
+impl ResolveLengths for MarginWidth {
+    fn resolve_lengths(&mut self, font_size: f32) {
+        if let MarginWidth::Length(length) = self {
+            length.resolve_lengths(font_size);
         }
     }
 }
@@ -179,10 +247,21 @@ impl<'i> ParseableProperty<'i> for BorderWidth {
 }
 
 impl BorderWidth {
+    /// Width in px, keywords use the same widths as major browsers.
     pub fn to_px(&self) -> f32 {
         match self {
+            BorderWidth::Thin => 1.0,
+            BorderWidth::Medium => 3.0,
+            BorderWidth::Thick => 5.0,
             BorderWidth::Length(length) => length.to_px(),
-            _ => todo!(),
+        }
+    }
+}
+
+impl ResolveLengths for BorderWidth {
+    fn resolve_lengths(&mut self, font_size: f32) {
+        if let BorderWidth::Length(length) = self {
+            length.resolve_lengths(font_size);
         }
     }
 }
@@ -259,3 +338,374 @@ impl<'i> ParseableProperty<'i> for Uri {
         Url::parse(&s).map(Uri).map_err(|_| ())
     }
 }
+
+/// Keyword from the table of font sizes, see [CSS 2.1
+/// specification](https://www.w3.org/TR/2011/REC-CSS2-20110607/fonts.html#font-size-props).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AbsoluteSize {
+    XxSmall,
+    XSmall,
+    Small,
+    Medium,
+    Large,
+    XLarge,
+    XxLarge,
+}
+
+impl<'i> ParseableProperty<'i> for AbsoluteSize {
+    fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ()> {
+        let ident = input.expect_ident().map_err(|_| ())?;
+        match ident.to_ascii_lowercase().as_str() {
+            "xx-small" => Ok(Self::XxSmall),
+            "x-small" => Ok(Self::XSmall),
+            "small" => Ok(Self::Small),
+            "medium" => Ok(Self::Medium),
+            "large" => Ok(Self::Large),
+            "x-large" => Ok(Self::XLarge),
+            "xx-large" => Ok(Self::XxLarge),
+            _ => Err(()),
+        }
+    }
+}
+
+impl AbsoluteSize {
+    /// Size in px, values are taken from the table in CSS Fonts Level 3 with `medium` being 16px.
+    pub fn to_px(&self) -> f32 {
+        match self {
+            AbsoluteSize::XxSmall => 9.0,
+            AbsoluteSize::XSmall => 10.0,
+            AbsoluteSize::Small => 13.0,
+            AbsoluteSize::Medium => 16.0,
+            AbsoluteSize::Large => 18.0,
+            AbsoluteSize::XLarge => 24.0,
+            AbsoluteSize::XxLarge => 32.0,
+        }
+    }
+}
+
+/// Font size relative to the font size of the parent element.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelativeSize {
+    Larger,
+    Smaller,
+}
+
+impl<'i> ParseableProperty<'i> for RelativeSize {
+    fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ()> {
+        let ident = input.expect_ident().map_err(|_| ())?;
+        match ident.to_ascii_lowercase().as_str() {
+            "larger" => Ok(Self::Larger),
+            "smaller" => Ok(Self::Smaller),
+            _ => Err(()),
+        }
+    }
+}
+
+impl RelativeSize {
+    /// Ratio between adjacent entries of the absolute size table.
+    pub const SCALE: f32 = 1.2;
+
+    /// Apply relative size to the font size of the parent.
+    pub fn to_px(&self, parent_font_size: f32) -> f32 {
+        match self {
+            RelativeSize::Larger => parent_font_size * Self::SCALE,
+            RelativeSize::Smaller => parent_font_size / Self::SCALE,
+        }
+    }
+}
+
+/// Single entry of the `font-family` list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FamilyName {
+    Serif,
+    SansSerif,
+    Cursive,
+    Fantasy,
+    Monospace,
+    /// Name of a concrete font family, e.g. `"Times New Roman"` or `Helvetica`.
+    Named(String),
+}
+
+impl<'i> ParseableProperty<'i> for FamilyName {
+    fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ()> {
+        if let Ok(name) = input.try_parse(|input| input.expect_string_cloned()) {
+            return Ok(Self::Named(name.to_string()));
+        }
+
+        // Unquoted family name is a sequence of identifiers separated by whitespace.
+        let mut idents = vec![input.expect_ident_cloned().map_err(|_| ())?];
+        while let Ok(ident) = input.try_parse(|input| input.expect_ident_cloned()) {
+            idents.push(ident);
+        }
+
+        if let [ident] = idents.as_slice() {
+            match ident.to_ascii_lowercase().as_str() {
+                "serif" => return Ok(Self::Serif),
+                "sans-serif" => return Ok(Self::SansSerif),
+                "cursive" => return Ok(Self::Cursive),
+                "fantasy" => return Ok(Self::Fantasy),
+                "monospace" => return Ok(Self::Monospace),
+                _ => {}
+            }
+        }
+
+        Ok(Self::Named(
+            idents
+                .iter()
+                .map(|ident| ident.as_ref())
+                .collect::<Vec<_>>()
+                .join(" "),
+        ))
+    }
+}
+
+/// Comma separated list of font families in order of preference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFamily(pub Vec<FamilyName>);
+
+impl<'i> ParseableProperty<'i> for FontFamily {
+    fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ()> {
+        input
+            .parse_comma_separated(|input| {
+                FamilyName::parse(input).map_err(|_| input.new_custom_error::<(), ()>(()))
+            })
+            .map(FontFamily)
+            .map_err(|_| ())
+    }
+}
+
+/// Style of a counter in `counter()` and `counters()`, one of the `list-style-type` keywords of
+/// CSS 2.1 except the `armenian` and `georgian` numbering.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CounterStyle {
+    Disc,
+    Circle,
+    Square,
+    Decimal,
+    DecimalLeadingZero,
+    LowerRoman,
+    UpperRoman,
+    LowerGreek,
+    LowerAlpha,
+    UpperAlpha,
+    None,
+}
+
+impl<'i> ParseableProperty<'i> for CounterStyle {
+    fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ()> {
+        let ident = input.expect_ident().map_err(|_| ())?;
+        match ident.to_ascii_lowercase().as_str() {
+            "disc" => Ok(Self::Disc),
+            "circle" => Ok(Self::Circle),
+            "square" => Ok(Self::Square),
+            "decimal" => Ok(Self::Decimal),
+            "decimal-leading-zero" => Ok(Self::DecimalLeadingZero),
+            "lower-roman" => Ok(Self::LowerRoman),
+            "upper-roman" => Ok(Self::UpperRoman),
+            "lower-greek" => Ok(Self::LowerGreek),
+            "lower-alpha" | "lower-latin" => Ok(Self::LowerAlpha),
+            "upper-alpha" | "upper-latin" => Ok(Self::UpperAlpha),
+            "none" => Ok(Self::None),
+            _ => Err(()),
+        }
+    }
+}
+
+impl CounterStyle {
+    /// Representation of counter `value` in this style. Values out of range of a style are
+    /// represented as decimal numbers.
+    pub fn format(self, value: i32) -> String {
+        match self {
+            Self::Disc => "\u{2022}".to_owned(),
+            Self::Circle => "\u{25e6}".to_owned(),
+            Self::Square => "\u{25aa}".to_owned(),
+            Self::Decimal => value.to_string(),
+            Self::DecimalLeadingZero if (0..10).contains(&value) => format!("0{value}"),
+            Self::DecimalLeadingZero if (-9..0).contains(&value) => format!("-0{}", -value),
+            Self::DecimalLeadingZero => value.to_string(),
+            Self::LowerRoman => roman(value).unwrap_or_else(|| value.to_string()),
+            Self::UpperRoman => {
+                roman(value).map_or_else(|| value.to_string(), |r| r.to_uppercase())
+            }
+            Self::LowerGreek => alphabetic(value, "αβγδεζηθικλμνξοπρστυφχψω"),
+            Self::LowerAlpha => alphabetic(value, "abcdefghijklmnopqrstuvwxyz"),
+            Self::UpperAlpha => alphabetic(value, "ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
+            Self::None => String::new(),
+        }
+    }
+}
+
+/// Lower case roman numeral of `value`, if it is in range from 1 to 3999.
+fn roman(mut value: i32) -> Option<String> {
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+    if !(1..4000).contains(&value) {
+        return None;
+    }
+    let mut numeral = String::new();
+    for (step, digits) in NUMERALS {
+        while value >= step {
+            numeral.push_str(digits);
+            value -= step;
+        }
+    }
+    Some(numeral)
+}
+
+/// Number `value` written with letters of `alphabet` as digits: `a`, `b`, ..., `z`, `aa`, `ab`,
+/// and so on. Values below 1 are written as decimal numbers.
+fn alphabetic(value: i32, alphabet: &str) -> String {
+    let letters: Vec<char> = alphabet.chars().collect();
+    if value < 1 {
+        return value.to_string();
+    }
+    let mut value = value as usize;
+    let mut digits = Vec::new();
+    while value > 0 {
+        value -= 1;
+        digits.push(letters[value % letters.len()]);
+        value /= letters.len();
+    }
+    digits.iter().rev().collect()
+}
+
+/// Single item of the `content` property.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentItem {
+    String(String),
+    Uri(Uri),
+    /// `counter(name, style)`, value of the innermost counter with the name.
+    Counter(String, CounterStyle),
+    /// `counters(name, separator, style)`, values of all counters with the name in scope, from the
+    /// outermost one, joined with the separator.
+    Counters(String, String, CounterStyle),
+    /// `attr(name)`, value of the attribute of the element, or nothing if it is not set.
+    Attr(String),
+    OpenQuote,
+    CloseQuote,
+    NoOpenQuote,
+    NoCloseQuote,
+}
+
+impl<'i> ParseableProperty<'i> for ContentItem {
+    fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ()> {
+        if let Ok(string) = input.try_parse(|input| input.expect_string_cloned()) {
+            return Ok(Self::String(string.to_string()));
+        }
+        if let Ok(uri) = input.try_parse(Uri::parse) {
+            return Ok(Self::Uri(uri));
+        }
+
+        match input.next().map_err(|_| ())?.clone() {
+            cssparser::Token::Ident(ident) => match ident.to_ascii_lowercase().as_str() {
+                "open-quote" => Ok(Self::OpenQuote),
+                "close-quote" => Ok(Self::CloseQuote),
+                "no-open-quote" => Ok(Self::NoOpenQuote),
+                "no-close-quote" => Ok(Self::NoCloseQuote),
+                _ => Err(()),
+            },
+            cssparser::Token::Function(name) => input
+                .parse_nested_block(|input| {
+                    Self::parse_function(&name, input)
+                        .map_err(|()| input.new_custom_error::<(), ()>(()))
+                })
+                .map_err(|_| ()),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ContentItem {
+    /// Parse arguments of function `name`.
+    fn parse_function(name: &str, input: &mut Parser) -> Result<Self, ()> {
+        let style = |input: &mut Parser| {
+            if input.try_parse(|input| input.expect_comma()).is_ok() {
+                CounterStyle::parse(input)
+            } else {
+                Ok(CounterStyle::Decimal)
+            }
+        };
+
+        match name.to_ascii_lowercase().as_str() {
+            "attr" => Ok(Self::Attr(
+                input.expect_ident().map_err(|_| ())?.to_string(),
+            )),
+            "counter" => {
+                let name = counter_name(input)?;
+                Ok(Self::Counter(name, style(input)?))
+            }
+            "counters" => {
+                let name = counter_name(input)?;
+                input.expect_comma().map_err(|_| ())?;
+                let separator = input.expect_string().map_err(|_| ())?.to_string();
+                Ok(Self::Counters(name, separator, style(input)?))
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+/// Name of a counter, keywords of counter properties can not be used as names.
+fn counter_name(input: &mut Parser) -> Result<String, ()> {
+    let name = input.expect_ident().map_err(|_| ())?;
+    match name.to_ascii_lowercase().as_str() {
+        "none" | "inherit" | "initial" => Err(()),
+        _ => Ok(name.to_string()),
+    }
+}
+
+/// Value of the `content` property other than `normal` and `none`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentList(pub Vec<ContentItem>);
+
+impl<'i> ParseableProperty<'i> for ContentList {
+    fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ()> {
+        let mut items = vec![ContentItem::parse(input)?];
+        while let Ok(item) = input.try_parse(ContentItem::parse) {
+            items.push(item);
+        }
+        Ok(Self(items))
+    }
+}
+
+/// Counters with optional integers, as in `counter-reset: chapter section 2`. Integer is absent if
+/// the property's default applies: 0 for `counter-reset` and 1 for `counter-increment`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CounterList(pub Vec<(String, Option<i32>)>);
+
+impl<'i> ParseableProperty<'i> for CounterList {
+    fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ()> {
+        let mut counters = Vec::new();
+        while let Ok(name) = input.try_parse(counter_name) {
+            let value = input.try_parse(|input| input.expect_integer()).ok();
+            counters.push((name, value));
+        }
+        if counters.is_empty() {
+            return Err(());
+        }
+        Ok(Self(counters))
+    }
+}
+
+// Values without lengths in them.
+impl ResolveLengths for Percentage {}
+impl ResolveLengths for Color {}
+impl ResolveLengths for Uri {}
+impl ResolveLengths for AbsoluteSize {}
+impl ResolveLengths for RelativeSize {}
+impl ResolveLengths for FontFamily {}
+impl ResolveLengths for ContentList {}
+impl ResolveLengths for CounterList {}
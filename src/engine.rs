@@ -0,0 +1,958 @@
+//! Embeddable facade over the whole pipeline: HTML parsing, styling, layout and painting.
+use std::collections::HashSet;
+
+use ego_tree::{NodeId, Tree};
+use log::debug;
+use url::Url;
+
+use crate::css::diagnostic::DocumentDiagnostic;
+use crate::css::media::{ColorScheme, Device};
+use crate::css::{self, StyleSheet};
+use crate::html::node::ElementState;
+use crate::html::{Element, Html, Node};
+use crate::layout::{self, Dimensions, LayoutBox, Rect};
+use crate::loader::{FileLoader, ResourceLoader};
+use crate::painting::{self, DisplayList, PixelBuffer};
+use crate::style::{self, StyledNode};
+
+/// Size of the area page is laid out into, in px.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Viewport {
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Loaded page together with results of every pipeline stage.
+#[derive(Debug)]
+pub struct Document {
+    source: String,
+    url: Option<Url>,
+    html: Html,
+    stylesheet: StyleSheet,
+    diagnostics: Vec<DocumentDiagnostic>,
+    /// Device media queries were evaluated against when the style tree was built.
+    device: Device,
+    style_tree: Tree<StyledNode>,
+    layout_tree: Tree<LayoutBox>,
+    /// Element the pointer was pressed on, it is activated if the pointer is released over it.
+    pressed: Option<NodeId>,
+}
+
+impl Document {
+    /// URL the document was loaded from, relative URLs in the document are resolved against it.
+    pub fn url(&self) -> Option<&Url> {
+        self.url.as_ref()
+    }
+
+    /// Parsed HTML of the page.
+    pub fn html(&self) -> &Html {
+        &self.html
+    }
+
+    /// Author style sheet collected from the page, including linked and imported style sheets.
+    pub fn stylesheet(&self) -> &StyleSheet {
+        &self.stylesheet
+    }
+
+    /// Problems found in style sheets and `style` attributes of the page when it was loaded.
+    pub fn diagnostics(&self) -> &[DocumentDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Tree of nodes with their specified styles.
+    pub fn style_tree(&self) -> &Tree<StyledNode> {
+        &self.style_tree
+    }
+
+    /// Tree of boxes positioned during the last layout.
+    pub fn layout_tree(&self) -> &Tree<LayoutBox> {
+        &self.layout_tree
+    }
+
+    /// Height of the laid out page, i.e. bottom edge of the root margin box.
+    pub fn height(&self) -> f32 {
+        let margin_box = self.layout_tree.root().value().dimensions.margin_box();
+        margin_box.y + margin_box.height
+    }
+
+    /// Element at point `(x, y)` of the document, for text it is the element containing the text.
+    fn element_at(&self, x: f32, y: f32) -> Option<NodeId> {
+        let id = layout::hit_test(self.layout_tree.root(), x, y)?;
+        self.with_ancestors(Some(id)).first().copied()
+    }
+
+    /// Ids of the element containing node `id` and of all its ancestor elements, innermost first.
+    fn with_ancestors(&self, id: Option<NodeId>) -> Vec<NodeId> {
+        let Some(node) = id.and_then(|id| self.html.tree.get(id)) else {
+            return Vec::new();
+        };
+        std::iter::once(node)
+            .chain(node.ancestors())
+            .filter(|node| node.value().is_element())
+            .map(|node| node.id())
+            .collect()
+    }
+
+    fn element(&self, id: NodeId) -> &Element {
+        self.html
+            .tree
+            .get(id)
+            .unwrap()
+            .value()
+            .as_element()
+            .unwrap()
+    }
+
+    /// Activate element `id` like a click does: visit a link or check a checkbox or a radio button.
+    /// Links are activated from their descendants as well.
+    ///
+    /// Returns true if state of any element changed.
+    fn activate(&mut self, id: NodeId, visited: &mut HashSet<Url>) -> bool {
+        let Some(target) = self.with_ancestors(Some(id)).into_iter().find(|&id| {
+            let element = self.element(id);
+            (element.is_link() || element.is_checkable()) && !element.state.disabled
+        }) else {
+            return false;
+        };
+
+        let element = self.element(target);
+        if element.is_link() {
+            let Some(url) = link_url(element, self.url.as_ref()) else {
+                return false;
+            };
+            debug!("Visiting {url}");
+            visited.insert(url);
+            mark_visited(&mut self.html, self.url.as_ref(), visited)
+        } else if is_radio(element) {
+            // Only one radio button of a group is checked, radio buttons without name are not
+            // grouped.
+            let group = element.attr("name").filter(|name| !name.is_empty());
+            let group = group.map(str::to_owned);
+            update_state(&mut self.html, checked, |id, element| {
+                if id == target {
+                    true
+                } else if is_radio(element)
+                    && group.is_some()
+                    && element.attr("name") == group.as_deref()
+                {
+                    false
+                } else {
+                    element.state.checked
+                }
+            })
+        } else {
+            update_state(&mut self.html, checked, |id, element| {
+                element.state.checked != (id == target)
+            })
+        }
+    }
+}
+
+fn hover(state: &mut ElementState) -> &mut bool {
+    &mut state.hover
+}
+
+fn active(state: &mut ElementState) -> &mut bool {
+    &mut state.active
+}
+
+fn focus(state: &mut ElementState) -> &mut bool {
+    &mut state.focus
+}
+
+fn visited(state: &mut ElementState) -> &mut bool {
+    &mut state.visited
+}
+
+fn checked(state: &mut ElementState) -> &mut bool {
+    &mut state.checked
+}
+
+/// Set `flag` of every element in `html` to the value `value` returns for the element.
+///
+/// Returns true if the flag changed for any element.
+fn update_state(
+    html: &mut Html,
+    flag: fn(&mut ElementState) -> &mut bool,
+    mut value: impl FnMut(NodeId, &Element) -> bool,
+) -> bool {
+    let mut changed = false;
+    html.tree.root_mut().for_each_descendant(|node| {
+        let id = node.id();
+        if let Node::Element(element) = node.value() {
+            let new = value(id, element);
+            let old = std::mem::replace(flag(&mut element.state), new);
+            changed |= old != new;
+        }
+    });
+    changed
+}
+
+/// Mark links in `html` to any of `visited` URLs as visited.
+fn mark_visited(html: &mut Html, base: Option<&Url>, visited: &HashSet<Url>) -> bool {
+    update_state(html, self::visited, |_, element| {
+        link_url(element, base).is_some_and(|url| visited.contains(&url))
+    })
+}
+
+/// URL a link element points to, resolved against `base`.
+fn link_url(element: &Element, base: Option<&Url>) -> Option<Url> {
+    if !element.is_link() {
+        return None;
+    }
+    Url::options()
+        .base_url(base)
+        .parse(element.attr("href")?)
+        .ok()
+}
+
+fn is_radio(element: &Element) -> bool {
+    element.is_checkable()
+        && element
+            .attr("type")
+            .is_some_and(|ty| ty.eq_ignore_ascii_case("radio"))
+}
+
+/// Browser engine that renders a single document into a viewport.
+///
+/// Stages of the pipeline are exposed as separate steps, so embedders decide when to run them.
+/// Results of every stage are cached and recomputed only when their inputs change: loading the
+/// same source again is a no-op, a viewport change only invalidates layout, unless it changes
+/// which media queries match, and painting reuses display list built for the current layout.
+///
+/// Style sheets referenced by the document are fetched with a [`ResourceLoader`], by default
+/// [`FileLoader`].
+///
+/// Documents taller than the viewport are scrolled vertically, the scroll offset is kept within
+/// the document as it is reloaded or laid out again.
+///
+/// Pointer and keyboard input reported by the embedder updates state of elements, which is matched
+/// by pseudo-classes like `:hover`, `:focus` or `:checked`. Activated links are remembered for
+/// the lifetime of the engine and match `:visited`.
+///
+/// ```
+/// use wbp::engine::{Engine, Viewport};
+///
+/// let mut engine = Engine::new(Viewport { width: 800.0, height: 600.0 });
+/// engine.load_html("<div>Hello</div>");
+///
+/// engine.set_viewport(Viewport { width: 400.0, height: 300.0 });
+/// engine.relayout();
+///
+/// engine.scroll_by(100.0);
+/// engine.pointer_moved(10.0, 10.0);
+///
+/// let mut pixmap = tiny_skia::Pixmap::new(400, 300).unwrap();
+/// engine.paint_into(&mut pixmap);
+/// ```
+#[derive(Debug)]
+pub struct Engine {
+    viewport: Viewport,
+    color_scheme: ColorScheme,
+    /// Style sheet of the user origin, cascaded together with author style sheets.
+    user_stylesheet: StyleSheet,
+    loader: Box<dyn ResourceLoader>,
+    document: Option<Document>,
+    /// Document was styled and laid out for another viewport or color scheme.
+    needs_layout: bool,
+    /// User style sheet or state of elements changed since the document was styled.
+    needs_style: bool,
+    /// Display list for the current layout tree, built on the first paint.
+    display_list: Option<DisplayList>,
+    /// Distance from the top of the document to the top of the viewport.
+    scroll_y: f32,
+    /// Position of the pointer in the viewport, if it is over the viewport.
+    pointer: Option<(f32, f32)>,
+    /// URLs of links activated by the user.
+    visited: HashSet<Url>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self {
+            viewport: Viewport::default(),
+            color_scheme: ColorScheme::default(),
+            user_stylesheet: StyleSheet::new(),
+            loader: Box::new(FileLoader),
+            document: None,
+            needs_layout: false,
+            needs_style: false,
+            display_list: None,
+            scroll_y: 0.0,
+            pointer: None,
+            visited: HashSet::new(),
+        }
+    }
+}
+
+impl Engine {
+    /// Create engine without any document loaded.
+    pub fn new(viewport: Viewport) -> Self {
+        Self {
+            viewport,
+            ..Default::default()
+        }
+    }
+
+    /// Current viewport.
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// Currently loaded document, if any.
+    pub fn document(&self) -> Option<&Document> {
+        self.document.as_ref()
+    }
+
+    /// Set loader used to fetch resources of documents loaded afterwards.
+    pub fn set_resource_loader(&mut self, loader: impl ResourceLoader + 'static) {
+        self.loader = Box::new(loader);
+    }
+
+    /// Parse, style and lay out HTML `source`, replacing previously loaded document.
+    ///
+    /// Document has no URL, so only resources with absolute URLs can be loaded. See
+    /// [`Engine::load_html_with_url`].
+    ///
+    /// Returns `false` and keeps the current document if `source` did not change since the last
+    /// load.
+    pub fn load_html(&mut self, source: &str) -> bool {
+        self.load_html_with_url(source, None)
+    }
+
+    /// Like [`Engine::load_html`], but relative URLs in the document are resolved against `url`.
+    ///
+    /// Returns `false` and keeps the current document if neither `source` nor `url` changed since
+    /// the last load. Resources of the document are not checked for changes.
+    pub fn load_html_with_url(&mut self, source: &str, url: Option<Url>) -> bool {
+        if self
+            .document
+            .as_ref()
+            .is_some_and(|document| document.source == source && document.url == url)
+        {
+            debug!("Source did not change, keeping loaded document");
+            return false;
+        }
+
+        let mut html = Html::parse_fragment(source);
+        mark_visited(&mut html, url.as_ref(), &self.visited);
+        // debug!("Document tree: {:#?}", html.tree);
+        let (stylesheet, diagnostics) = css::from_document(&html, url.as_ref(), &*self.loader);
+        let device = self.device();
+        let style_tree = style::style_tree(&html.tree, &stylesheet, &self.user_stylesheet, &device);
+        let layout_tree = layout_style_tree(&style_tree, self.viewport);
+
+        self.document = Some(Document {
+            source: source.to_owned(),
+            url,
+            html,
+            stylesheet,
+            diagnostics,
+            device,
+            style_tree,
+            layout_tree,
+            pressed: None,
+        });
+        self.needs_layout = false;
+        self.needs_style = false;
+        self.display_list = None;
+        true
+    }
+
+    /// Change viewport size. Takes effect on the next [`Engine::relayout`] or paint.
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        if self.viewport != viewport {
+            self.viewport = viewport;
+            self.needs_layout = true;
+        }
+    }
+
+    /// Change color scheme preferred by the user, which is matched by `prefers-color-scheme` media
+    /// queries. Takes effect on the next [`Engine::relayout`] or paint.
+    pub fn set_color_scheme(&mut self, color_scheme: ColorScheme) {
+        if self.color_scheme != color_scheme {
+            self.color_scheme = color_scheme;
+            self.needs_layout = true;
+        }
+    }
+
+    /// Set style sheet of the user origin, e.g. to enlarge fonts or force a dark theme. Takes effect
+    /// on the next [`Engine::relayout`] or paint.
+    ///
+    /// Normal user declarations are overridden by author ones, but important user declarations
+    /// override everything else.
+    pub fn set_user_stylesheet(&mut self, stylesheet: StyleSheet) {
+        self.user_stylesheet = stylesheet;
+        self.needs_style = true;
+    }
+
+    /// Lay out loaded document again if viewport changed since the last layout.
+    ///
+    /// Document is styled again first if user style sheet or state of elements changed, or rules
+    /// with media queries start or stop to apply.
+    pub fn relayout(&mut self) {
+        if !self.needs_layout && !self.needs_style {
+            return;
+        }
+        self.needs_layout = false;
+
+        let device = self.device();
+        if let Some(document) = &mut self.document {
+            let media_changed = document
+                .stylesheet
+                .iter()
+                .chain(&self.user_stylesheet)
+                .any(|rule| rule.matches_media(&document.device) != rule.matches_media(&device));
+            if self.needs_style || media_changed {
+                debug!("Styles changed, styling document again");
+                document.style_tree = style::style_tree(
+                    &document.html.tree,
+                    &document.stylesheet,
+                    &self.user_stylesheet,
+                    &device,
+                );
+            }
+            document.device = device;
+            self.needs_style = false;
+
+            debug!("Laying out document again");
+            document.layout_tree = layout_style_tree(&document.style_tree, self.viewport);
+            self.display_list = None;
+        }
+    }
+
+    /// Device media queries are evaluated against.
+    fn device(&self) -> Device {
+        Device {
+            width: self.viewport.width,
+            height: self.viewport.height,
+            color_scheme: self.color_scheme,
+        }
+    }
+
+    /// Current vertical scroll offset in px.
+    pub fn scroll_offset(&self) -> f32 {
+        self.scroll_y
+    }
+
+    /// Scroll so that `y` of the document is at the top of the viewport.
+    ///
+    /// Offset is clamped, so the viewport never goes past the top or the bottom of the document.
+    pub fn scroll_to(&mut self, y: f32) {
+        self.relayout();
+        self.scroll_y = y.clamp(0.0, self.max_scroll_offset());
+    }
+
+    /// Scroll by `dy` px, positive values scroll down. See [`Engine::scroll_to`].
+    pub fn scroll_by(&mut self, dy: f32) {
+        self.scroll_to(self.scroll_y + dy);
+    }
+
+    /// Largest scroll offset at which the bottom of the document is still in the viewport.
+    fn max_scroll_offset(&self) -> f32 {
+        self.document.as_ref().map_or(0.0, |document| {
+            (document.height() - self.viewport.height).max(0.0)
+        })
+    }
+
+    /// Pointer moved to `(x, y)` of the viewport. Element under the pointer and its ancestors
+    /// match `:hover`. Takes effect on the next [`Engine::relayout`] or paint.
+    pub fn pointer_moved(&mut self, x: f32, y: f32) {
+        self.pointer = Some((x, y));
+        self.update_hover();
+    }
+
+    /// Pointer left the viewport, nothing matches `:hover` anymore.
+    pub fn pointer_left(&mut self) {
+        self.pointer = None;
+        self.update_hover();
+    }
+
+    /// Pointer button was pressed. Element under the pointer and its ancestors match `:active`,
+    /// the nearest focusable one gets focus.
+    pub fn pointer_pressed(&mut self) {
+        let target = self.pointer_target();
+        let Some(document) = &mut self.document else {
+            return;
+        };
+        let chain = document.with_ancestors(target);
+        let focused = chain
+            .iter()
+            .copied()
+            .find(|&id| document.element(id).is_focusable());
+        document.pressed = target;
+        let active = update_state(&mut document.html, active, |id, _| chain.contains(&id));
+        let focus = update_state(&mut document.html, focus, |id, _| Some(id) == focused);
+        self.needs_style |= active || focus;
+    }
+
+    /// Pointer button was released. Element it was pressed on is activated if the pointer is still
+    /// over it, e.g. a link becomes visited or a checkbox is toggled.
+    pub fn pointer_released(&mut self) {
+        let target = self.pointer_target();
+        let Some(document) = &mut self.document else {
+            return;
+        };
+        let pressed = document.pressed.take();
+        let mut changed = update_state(&mut document.html, active, |_, _| false);
+        if let Some(target) = target.filter(|&target| Some(target) == pressed) {
+            changed |= document.activate(target, &mut self.visited);
+        }
+        self.needs_style |= changed;
+    }
+
+    /// Move focus to the next focusable element in document order, or to the previous one if
+    /// `backwards` is true, as `Tab` and `Shift+Tab` do. Focus wraps around the document.
+    pub fn focus_next(&mut self, backwards: bool) {
+        let Some(document) = &mut self.document else {
+            return;
+        };
+        let focusable: Vec<_> = document
+            .html
+            .tree
+            .root()
+            .descendants()
+            .filter(|node| node.value().as_element().is_some_and(Element::is_focusable))
+            .map(|node| node.id())
+            .collect();
+        let current = focusable
+            .iter()
+            .position(|&id| document.element(id).state.focus);
+        let next = match (current, backwards) {
+            (None, false) => focusable.first(),
+            (None, true) => focusable.last(),
+            (Some(i), false) => focusable.get((i + 1) % focusable.len()),
+            (Some(i), true) => focusable.get((i + focusable.len() - 1) % focusable.len()),
+        };
+        let next = next.copied();
+        self.needs_style |= update_state(&mut document.html, focus, |id, _| Some(id) == next);
+    }
+
+    /// Activate the focused element as `Enter` does, see [`Engine::pointer_released`].
+    pub fn activate_focused(&mut self) {
+        let Some(document) = &mut self.document else {
+            return;
+        };
+        let focused = document.html.tree.root().descendants().find(|node| {
+            node.value()
+                .as_element()
+                .is_some_and(|element| element.state.focus)
+        });
+        if let Some(id) = focused.map(|node| node.id()) {
+            self.needs_style |= document.activate(id, &mut self.visited);
+        }
+    }
+
+    /// Element under the pointer in the last layout. Pending style changes are not laid out
+    /// first, so a burst of input restyles the document once.
+    fn pointer_target(&self) -> Option<NodeId> {
+        let (x, y) = self.pointer?;
+        self.document.as_ref()?.element_at(x, y + self.scroll_y)
+    }
+
+    fn update_hover(&mut self) {
+        let target = self.pointer_target();
+        let Some(document) = &mut self.document else {
+            return;
+        };
+        let chain = document.with_ancestors(target);
+        self.needs_style |= update_state(&mut document.html, hover, |id, _| chain.contains(&id));
+    }
+
+    /// Paint loaded document into `buffer`, scrolled by the current offset. Does nothing if no
+    /// document is loaded.
+    ///
+    /// Lays out document first if it is out of date.
+    pub fn paint_into(&mut self, buffer: &mut impl PixelBuffer) {
+        // Document could get shorter since the last scroll.
+        self.scroll_to(self.scroll_y);
+
+        let Some(document) = &self.document else {
+            return;
+        };
+        let display_list = self
+            .display_list
+            .get_or_insert_with(|| painting::build_display_list(document.layout_tree.root()));
+        buffer.paint_display_list(display_list, -self.scroll_y);
+    }
+}
+
+fn layout_style_tree(style_tree: &Tree<StyledNode>, viewport: Viewport) -> Tree<LayoutBox> {
+    let screen_dimensions = Dimensions {
+        content: Rect {
+            width: viewport.width,
+            height: viewport.height,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    layout::layout_tree(
+        style_tree.root().first_child().unwrap(), // Omit Document node as it treated as inline
+        screen_dimensions,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::diagnostic::Reason;
+    use crate::selector::PseudoElement;
+
+    const SOURCE: &str = "<div>Hello</div>";
+
+    fn engine(width: f32) -> Engine {
+        Engine::new(Viewport {
+            width,
+            height: 600.0,
+        })
+    }
+
+    #[test]
+    fn same_source_is_not_reparsed() {
+        let mut engine = engine(800.0);
+
+        assert!(engine.load_html(SOURCE));
+        assert!(!engine.load_html(SOURCE));
+        assert!(engine.load_html("<div>Changed</div>"));
+    }
+
+    #[test]
+    fn resize_relayouts_and_drops_display_list() {
+        let mut engine = engine(800.0);
+        engine.load_html(SOURCE);
+
+        let mut pixmap = tiny_skia::Pixmap::new(800, 600).unwrap();
+        engine.paint_into(&mut pixmap);
+        assert!(engine.display_list.is_some());
+
+        // Same size keeps everything cached.
+        engine.set_viewport(Viewport {
+            width: 800.0,
+            height: 600.0,
+        });
+        assert!(!engine.needs_layout);
+        assert!(engine.display_list.is_some());
+
+        engine.set_viewport(Viewport {
+            width: 400.0,
+            height: 300.0,
+        });
+        assert!(engine.needs_layout);
+        engine.relayout();
+        assert!(!engine.needs_layout);
+        assert!(engine.display_list.is_none());
+        let root = engine.document().unwrap().layout_tree().root();
+        assert_eq!(root.value().dimensions.content.width, 400.0);
+    }
+
+    #[test]
+    fn unsupported_styles_do_not_panic() {
+        let mut engine = engine(800.0);
+        let mut pixmap = tiny_skia::Pixmap::new(800, 600).unwrap();
+
+        engine.load_html(
+            r#"<div style="margin: 1px 2px 3px; border-width: 1px 2px 3px; padding: 1px 2px 3px">
+                <p style="width: -10px; background: red; border-color: transparent">x</p>
+            </div>"#,
+        );
+        engine.paint_into(&mut pixmap);
+
+        engine.load_html(r#"<style>p { display: table }</style><p>Table</p>"#);
+        engine.paint_into(&mut pixmap);
+        let diagnostics = engine.document().unwrap().diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].diagnostic.reason, Reason::UnsupportedValue);
+
+        engine.load_html(r#"<style>html { display: none }</style><p>Hidden</p>"#);
+        engine.paint_into(&mut pixmap);
+        assert_eq!(engine.document().unwrap().height(), 0.0);
+    }
+
+    #[test]
+    fn narrow_viewport_wraps_text_into_more_lines() {
+        crate::text::load_test_font();
+
+        let source = r#"<div style="font-family: Tuffy">Some words to wrap</div>"#;
+        let mut engine = engine(800.0);
+        engine.load_html(source);
+        let wide = engine.document().unwrap().height();
+
+        engine.set_viewport(Viewport {
+            width: 1.0,
+            height: 600.0,
+        });
+        engine.relayout();
+        let narrow = engine.document().unwrap().height();
+
+        assert!(wide > 0.0);
+        // One word per line.
+        assert!(narrow >= 4.0 * wide * 0.9, "{narrow} vs {wide}");
+    }
+
+    #[test]
+    fn scroll_offset_is_clamped_to_document() {
+        let source = r#"<style>div { display: block; height: 1000px; }</style><div></div>"#;
+        let mut engine = engine(800.0);
+        engine.load_html(source);
+
+        engine.scroll_by(-50.0);
+        assert_eq!(engine.scroll_offset(), 0.0);
+        engine.scroll_by(300.0);
+        assert_eq!(engine.scroll_offset(), 300.0);
+        engine.scroll_to(f32::INFINITY);
+        assert_eq!(engine.scroll_offset(), 400.0);
+
+        // Taller viewport leaves less to scroll.
+        engine.set_viewport(Viewport {
+            width: 800.0,
+            height: 900.0,
+        });
+        engine.scroll_by(0.0);
+        assert_eq!(engine.scroll_offset(), 100.0);
+    }
+
+    #[test]
+    fn percentages_refer_to_containing_block() {
+        let source = r#"<style>
+            html { height: 100%; }
+            div { display: block; }
+            .outer { height: 50%; }
+            .inner { width: 50%; height: 25%; margin-left: 10%; padding-top: 5%; }
+        </style><div class="outer"><div class="inner"></div></div>"#;
+        let mut engine = engine(800.0);
+        engine.load_html(source);
+
+        let root = engine.document().unwrap().layout_tree().root();
+        let outer = root.last_child().unwrap();
+        let inner = outer.first_child().unwrap().value().dimensions;
+        assert_eq!(root.value().dimensions.content.height, 600.0);
+        assert_eq!(outer.value().dimensions.content.height, 300.0);
+        assert_eq!(inner.content.width, 400.0);
+        assert_eq!(inner.content.height, 75.0);
+        assert_eq!(inner.margin.left, 80.0);
+        assert_eq!(inner.padding.top, 40.0);
+    }
+
+    #[test]
+    fn box_shorthands_set_every_side() {
+        let source = r#"<style>
+            div { display: block; width: 100px; margin: 1px 2px 3px; padding: 4px 5px; }
+        </style><div></div>"#;
+        let mut engine = engine(800.0);
+        engine.load_html(source);
+
+        let root = engine.document().unwrap().layout_tree().root();
+        let d = root.last_child().unwrap().value().dimensions;
+        let edges = |e: crate::layout::EdgeSizes| [e.top, e.right, e.bottom, e.left];
+        assert_eq!(edges(d.padding), [4.0, 5.0, 4.0, 5.0]);
+        // Right margin takes the rest of the line, as the block is overconstrained.
+        assert_eq!(edges(d.margin), [1.0, 800.0 - 100.0 - 2.0 - 10.0, 3.0, 2.0]);
+    }
+
+    #[test]
+    fn input_updates_state_pseudo_classes() {
+        let source = r#"<style>
+            a, input { display: block; height: 10px; }
+            a:hover { height: 20px; }
+            a:visited { height: 30px; }
+            input:checked { height: 40px; }
+            input:focus { width: 50px; }
+        </style><input type="checkbox"><a href="page.html"></a>"#;
+        let sizes = |engine: &mut Engine| {
+            engine.relayout();
+            let root = engine.document().unwrap().layout_tree().root();
+            root.children()
+                .filter(|child| matches!(child.value().box_type, layout::BlockNode(_)))
+                .map(|child| {
+                    let content = child.value().dimensions.content;
+                    (content.width, content.height)
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut engine = engine(800.0);
+        let url = Url::parse("file:///index.html").ok();
+        engine.load_html_with_url(source, url.clone());
+        assert_eq!(sizes(&mut engine), [(800.0, 10.0), (800.0, 10.0)]);
+
+        engine.pointer_moved(5.0, 15.0);
+        assert_eq!(sizes(&mut engine), [(800.0, 10.0), (800.0, 20.0)]);
+
+        engine.pointer_moved(5.0, 5.0);
+        engine.pointer_pressed();
+        engine.pointer_released();
+        // Clicked checkbox gets focus too.
+        assert_eq!(sizes(&mut engine), [(50.0, 40.0), (800.0, 10.0)]);
+
+        // Focus wraps around to the checkbox again.
+        engine.focus_next(false);
+        engine.focus_next(false);
+        engine.activate_focused();
+        assert_eq!(sizes(&mut engine), [(50.0, 10.0), (800.0, 10.0)]);
+
+        engine.pointer_moved(5.0, 15.0);
+        engine.pointer_pressed();
+        engine.pointer_released();
+        engine.pointer_left();
+        // Link takes focus from the checkbox.
+        assert_eq!(sizes(&mut engine), [(800.0, 10.0), (800.0, 30.0)]);
+
+        // Visited links are remembered across loads.
+        engine.load_html_with_url(&source.replace("checkbox", "radio"), url);
+        assert_eq!(sizes(&mut engine), [(800.0, 10.0), (800.0, 30.0)]);
+    }
+
+    #[test]
+    fn pseudo_elements_get_boxes() {
+        let source = r#"<style>
+            p { display: block; }
+            p::before { content: ""; display: block; height: 5px; }
+            p::after { content: attr(title); }
+        </style><p title="after">text</p>"#;
+        let mut engine = engine(800.0);
+        engine.load_html(source);
+
+        let root = engine.document().unwrap().layout_tree().root();
+        let pseudo_boxes: Vec<_> = root
+            .descendants()
+            .filter_map(|layout_box| match &layout_box.value().box_type {
+                layout::BlockNode(style) => Some((style.pseudo?, "block", layout_box)),
+                layout::InlineNode(style) => Some((style.pseudo?, "inline", layout_box)),
+                layout::AnonymousBlock => None,
+            })
+            .map(|(pseudo, kind, layout_box)| (pseudo, kind, layout_box.children().count()))
+            .collect();
+        // Content of `::after` is a text box inside of the pseudo-element box.
+        assert_eq!(
+            pseudo_boxes,
+            [
+                (PseudoElement::Before, "block", 0),
+                (PseudoElement::After, "inline", 1),
+                (PseudoElement::After, "inline", 0),
+            ]
+        );
+        let before = root
+            .descendants()
+            .find(|b| matches!(&b.value().box_type, layout::BlockNode(s) if s.pseudo.is_some()))
+            .unwrap();
+        assert_eq!(before.value().dimensions.content.height, 5.0);
+    }
+
+    #[test]
+    fn first_line_is_shaped_with_its_style() {
+        crate::text::load_test_font();
+
+        let source = r#"<style>
+            p { display: block; font-family: Tuffy; font-size: 10px; }
+            p::first-line { font-size: 40px; }
+        </style><p>one two three</p>"#;
+        let mut engine = engine(1.0);
+        engine.load_html(source);
+
+        let root = engine.document().unwrap().layout_tree().root();
+        let text = root
+            .descendants()
+            .find(|b| matches!(&b.value().box_type, layout::InlineNode(s) if matches!(s.node, Node::Text(_))))
+            .unwrap();
+        let heights: Vec<_> = text
+            .value()
+            .fragments
+            .iter()
+            .map(|fragment| fragment.height)
+            .collect();
+        assert_eq!(heights.len(), 3);
+        assert!(heights[0] > 3.0 * heights[1], "{heights:?}");
+        assert_eq!(heights[1], heights[2]);
+    }
+
+    #[test]
+    fn first_line_background_is_painted_behind_the_line() {
+        crate::text::load_test_font();
+
+        let source = r#"<style>
+            p { display: block; font-family: Tuffy; font-size: 10px; }
+            p::first-line { background-color: blue; }
+        </style><p>one two three</p>"#;
+        let mut engine = engine(1.0);
+        engine.load_html(source);
+
+        let root = engine.document().unwrap().layout_tree().root();
+        let text = root
+            .descendants()
+            .find(|b| matches!(&b.value().box_type, layout::InlineNode(s) if matches!(s.node, Node::Text(_))))
+            .unwrap();
+        let backgrounds: Vec<_> = painting::build_display_list(root)
+            .into_iter()
+            .filter_map(|command| match command {
+                painting::DisplayCommand::SolidColor(color, rect) => {
+                    Some((color, (rect.x, rect.y, rect.width, rect.height)))
+                }
+                _ => None,
+            })
+            .collect();
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = text.value().fragments[0];
+        assert_eq!(
+            backgrounds,
+            [(crate::css::values::Color(0, 0, 255), (x, y, width, height))]
+        );
+    }
+
+    #[test]
+    fn resize_reevaluates_media_queries() {
+        let source = r#"<style>
+            div { display: block; height: 10px; }
+            @media (max-width: 500px) { div { height: 20px; } }
+            @media (prefers-color-scheme: dark) { div { height: 30px; } }
+        </style><div></div>"#;
+        let div_height = |engine: &Engine| {
+            let root = engine.document().unwrap().layout_tree().root();
+            root.last_child().unwrap().value().dimensions.content.height
+        };
+        let mut engine = engine(800.0);
+        engine.load_html(source);
+        assert_eq!(div_height(&engine), 10.0);
+
+        engine.set_viewport(Viewport {
+            width: 400.0,
+            height: 600.0,
+        });
+        engine.relayout();
+        assert_eq!(div_height(&engine), 20.0);
+
+        engine.set_color_scheme(ColorScheme::Dark);
+        engine.relayout();
+        assert_eq!(div_height(&engine), 30.0);
+
+        engine.set_viewport(Viewport {
+            width: 800.0,
+            height: 600.0,
+        });
+        engine.set_color_scheme(ColorScheme::Light);
+        engine.relayout();
+        assert_eq!(div_height(&engine), 10.0);
+    }
+
+    #[test]
+    fn user_stylesheet_restyles_document() {
+        let source = r#"<style>div { display: block; height: 10px; }</style><div></div>"#;
+        let div_height = |engine: &Engine| {
+            let root = engine.document().unwrap().layout_tree().root();
+            root.last_child().unwrap().value().dimensions.content.height
+        };
+        let mut engine = engine(800.0);
+        engine.load_html(source);
+
+        engine.set_user_stylesheet(css::parse_stylesheet("div { height: 20px; }").0);
+        engine.relayout();
+        assert_eq!(div_height(&engine), 10.0);
+
+        engine.set_user_stylesheet(css::parse_stylesheet("div { height: 20px !important; }").0);
+        engine.relayout();
+        assert_eq!(div_height(&engine), 20.0);
+    }
+}
@@ -0,0 +1,40 @@
+//! Rendering of pages without a window, e.g. for screenshot comparison on CI.
+use crate::css::StyleSheet;
+use crate::engine::{Engine, Viewport};
+
+use tiny_skia::Pixmap;
+use url::Url;
+
+/// Render HTML `source` into a pixel map of the given `width`.
+///
+/// Relative URLs of style sheets are resolved against `url` of the document, `user_stylesheet` is
+/// applied with the user origin.
+///
+/// When `height` is `None` pixel map is made tall enough to fit the whole document.
+/// Returns `None` if resulting pixel map would be empty.
+pub fn render(
+    source: &str,
+    url: Option<Url>,
+    user_stylesheet: StyleSheet,
+    width: u32,
+    height: Option<u32>,
+) -> Option<Pixmap> {
+    let mut engine = Engine::new(Viewport {
+        width: width as f32,
+        height: height.unwrap_or_default() as f32,
+    });
+    engine.set_user_stylesheet(user_stylesheet);
+    engine.load_html_with_url(source, url);
+
+    let height = height.unwrap_or_else(|| {
+        engine
+            .document()
+            .map_or(0, |document| document.height().ceil() as u32)
+    });
+
+    let mut pixmap = Pixmap::new(width, height)?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    engine.paint_into(&mut pixmap);
+
+    Some(pixmap)
+}
@@ -8,7 +8,9 @@ use selectors::{
 use super::{ElementNode, ElementRef};
 use crate::selector::{CssLocalName, CssString, NonTSPseudoClass, PseudoElement, Simple};
 
-/// Note: will never match against non-tree-structure pseudo-classes.
+/// Note: non-tree-structural pseudo-classes are matched against [`ElementState`] of the element.
+///
+/// [`ElementState`]: crate::html::node::ElementState
 impl<E: ElementNode + Clone> Element for ElementRef<'_, E> {
     type Impl = Simple;
 
@@ -90,10 +92,21 @@ impl<E: ElementNode + Clone> Element for ElementRef<'_, E> {
 
     fn match_non_ts_pseudo_class(
         &self,
-        _pc: &NonTSPseudoClass,
+        pc: &NonTSPseudoClass,
         _context: &mut matching::MatchingContext<'_, Self::Impl>,
     ) -> bool {
-        false
+        let element = self.value();
+        let state = element.state;
+        match pc {
+            NonTSPseudoClass::Hover => state.hover,
+            NonTSPseudoClass::Active => state.active,
+            NonTSPseudoClass::Focus => state.focus,
+            NonTSPseudoClass::Link => element.is_link() && !state.visited,
+            NonTSPseudoClass::Visited => element.is_link() && state.visited,
+            NonTSPseudoClass::Checked => state.checked,
+            NonTSPseudoClass::Disabled => element.is_form_control() && state.disabled,
+            NonTSPseudoClass::Enabled => element.is_form_control() && !state.disabled,
+        }
     }
 
     fn match_pseudo_element(
@@ -105,7 +118,7 @@ impl<E: ElementNode + Clone> Element for ElementRef<'_, E> {
     }
 
     fn is_link(&self) -> bool {
-        self.value().name() == "link"
+        self.value().is_link()
     }
 
     fn is_html_slot_element(&self) -> bool {
@@ -189,6 +202,35 @@ mod tests {
         assert!(!element.is_link());
     }
 
+    #[test]
+    fn test_state_pseudo_classes() {
+        let html = "<a href='/'>link</a><a>anchor</a><input type=checkbox checked disabled>";
+        let mut fragment = Html::parse_fragment(html);
+        let matches = |fragment: &Html, selector: &str| {
+            let sel = SelectorGroup::parse(selector).unwrap();
+            fragment.select(&sel).count()
+        };
+
+        assert_eq!(matches(&fragment, "a:link"), 1);
+        assert_eq!(matches(&fragment, "a:visited, a:hover"), 0);
+        assert_eq!(matches(&fragment, "input:checked:disabled"), 1);
+        assert_eq!(matches(&fragment, "input:enabled"), 0);
+
+        let ids: Vec<_> = fragment
+            .select(&SelectorGroup::parse("a").unwrap())
+            .map(|element| element.id())
+            .collect();
+        for id in ids {
+            let mut node = fragment.tree.get_mut(id).unwrap();
+            let state = &mut node.value().as_element_mut().unwrap().state;
+            state.hover = true;
+            state.visited = true;
+        }
+        assert_eq!(matches(&fragment, "a:hover"), 2);
+        assert_eq!(matches(&fragment, "a:link"), 0);
+        assert_eq!(matches(&fragment, "a:visited"), 1);
+    }
+
     #[test]
     fn test_has_class() {
         let html = "<p class='my_class'>hey there</p>";
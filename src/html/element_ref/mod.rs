@@ -6,6 +6,7 @@ use std::ops::Deref;
 
 use ego_tree::iter::{Edge, Traverse};
 use ego_tree::NodeRef;
+use html5ever::serialize::{serialize, SerializeOpts, TraversalScope};
 use selectors::matching::SelectorCaches;
 
 use crate::html::node::Element;
@@ -103,27 +104,6 @@ impl<'a, E: ElementNode> ElementRef<'a, E> {
         }
     }
 
-    // fn serialize(&self, traversal_scope: TraversalScope) -> String {
-    //     let opts = SerializeOpts {
-    //         scripting_enabled: false, // It's not clear what this does.
-    //         traversal_scope,
-    //         create_missing_parent: false,
-    //     };
-    //     let mut buf = Vec::new();
-    //     serialize(&mut buf, self, opts).unwrap();
-    //     String::from_utf8(buf).unwrap()
-    // }
-
-    // /// Returns the HTML of this element.
-    // pub fn html(&self) -> String {
-    //     self.serialize(TraversalScope::IncludeNode)
-    // }
-
-    // /// Returns the inner HTML of this element.
-    // pub fn inner_html(&self) -> String {
-    //     self.serialize(TraversalScope::ChildrenOnly(None))
-    // }
-
     /// Returns the value of an attribute.
     pub fn attr(&self, attr: &str) -> Option<&'a str> {
         self.value().attr(attr)
@@ -141,7 +121,7 @@ impl<'a, E: ElementNode> ElementRef<'a, E> {
     /// # Example
     ///
     /// ```
-    /// # use scraper::Html;
+    /// # use wbp::html::Html;
     /// let fragment = Html::parse_fragment("foo<span>bar</span><a>baz</a>qux");
     ///
     /// let children = fragment.root_element().child_elements().map(|element| element.value().name()).collect::<Vec<_>>();
@@ -156,7 +136,7 @@ impl<'a, E: ElementNode> ElementRef<'a, E> {
     /// # Example
     ///
     /// ```
-    /// # use scraper::Html;
+    /// # use wbp::html::Html;
     /// let fragment = Html::parse_fragment("foo<span><b>bar</b></span><a><i>baz</i></a>qux");
     ///
     /// let descendants = fragment.root_element().descendent_elements().map(|element| element.value().name()).collect::<Vec<_>>();
@@ -167,6 +147,29 @@ impl<'a, E: ElementNode> ElementRef<'a, E> {
     }
 }
 
+impl ElementRef<'_, Node> {
+    fn serialize(&self, traversal_scope: TraversalScope) -> String {
+        let opts = SerializeOpts {
+            scripting_enabled: false, // It's not clear what this does.
+            traversal_scope,
+            create_missing_parent: false,
+        };
+        let mut buf = Vec::new();
+        serialize(&mut buf, self, opts).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    /// Returns the HTML of this element.
+    pub fn html(&self) -> String {
+        self.serialize(TraversalScope::IncludeNode)
+    }
+
+    /// Returns the inner HTML of this element.
+    pub fn inner_html(&self) -> String {
+        self.serialize(TraversalScope::ChildrenOnly(None))
+    }
+}
+
 impl<E: ElementNode> Debug for ElementRef<'_, E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         Debug::fmt(self.value(), f)
@@ -257,30 +260,54 @@ impl<'a, E: ElementNode> Iterator for Text<'a, E> {
 impl<E: ElementNode> FusedIterator for Text<'_, E> {}
 
 mod element;
-// mod serializable;
-
-// #[cfg(test)]
-// mod tests {
-//     use crate::html::selector::Selector;
-//     use crate::html::Html;
-
-//     #[test]
-//     fn test_scope() {
-//         let html = r"
-//             <div>
-//                 <b>1</b>
-//                 <span>
-//                     <span><b>2</b></span>
-//                     <b>3</b>
-//                 </span>
-//             </div>
-//         ";
-//         let fragment = Html::parse_fragment(html);
-//         let sel1 = Selector::parse("div > span").unwrap();
-//         let sel2 = Selector::parse(":scope > b").unwrap();
-
-//         let element1 = fragment.select(&sel1).next().unwrap();
-//         let element2 = element1.select(&sel2).next().unwrap();
-//         assert_eq!(element2.inner_html(), "3");
-//     }
-// }
+mod serializable;
+
+#[cfg(test)]
+mod tests {
+    use crate::html::Html;
+    use crate::selector::SelectorGroup;
+
+    #[test]
+    fn test_scope() {
+        let html = r"
+            <div>
+                <b>1</b>
+                <span>
+                    <span><b>2</b></span>
+                    <b>3</b>
+                </span>
+            </div>
+        ";
+        let fragment = Html::parse_fragment(html);
+        let sel1 = SelectorGroup::parse("div > span").unwrap();
+        let sel2 = SelectorGroup::parse(":scope > b").unwrap();
+
+        let element1 = fragment.select(&sel1).next().unwrap();
+        let element2 = element1.select(&sel2).next().unwrap();
+        assert_eq!(element2.inner_html(), "3");
+    }
+
+    #[test]
+    fn text_and_attributes_are_escaped() {
+        let fragment =
+            Html::parse_fragment(r#"<p title="a &quot;b&quot; &amp; c">1 &lt; 2 &amp; 3</p>"#);
+        let p = fragment
+            .select(&SelectorGroup::parse("p").unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(
+            p.html(),
+            r#"<p title="a &quot;b&quot; &amp; c">1 &lt; 2 &amp; 3</p>"#
+        );
+    }
+
+    #[test]
+    fn void_elements_have_no_end_tag() {
+        let fragment = Html::parse_fragment(r#"<div>a<br>b<img src="x.png"></div>"#);
+        let div = fragment
+            .select(&SelectorGroup::parse("div").unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(div.inner_html(), r#"a<br>b<img src="x.png">"#);
+    }
+}
@@ -3,7 +3,7 @@
 
 mod utils;
 
-use std::{error::Error, fmt::Display};
+use std::{borrow::Cow, error::Error, fmt::Display};
 
 use cssparser::{BasicParseErrorKind, ParseErrorKind, Token};
 use selectors::parser::SelectorParseErrorKind;
@@ -117,3 +117,199 @@ impl Error for SelectorErrorKind<'_> {
         }
     }
 }
+
+/// Error type that is returned when a mutation of `Html` tree is not allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutationError {
+    /// Node id does not belong to the tree
+    NotFound,
+
+    /// Node can't be inserted at the requested place, e.g. into a text node or into itself
+    HierarchyRequest,
+
+    /// Operation requires an element, but node is not one
+    NotAnElement,
+}
+
+impl Display for MutationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::NotFound => "The node was not found in the tree",
+                Self::HierarchyRequest => "The node can't be inserted at this place in the tree",
+                Self::NotAnElement => "The node is not an element",
+            }
+        )
+    }
+}
+
+impl Error for MutationError {}
+
+/// Kind of a problem found while parsing HTML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HtmlErrorKind {
+    /// Character that is not allowed at its place, e.g. a control character or `<` in an
+    /// attribute name
+    BadCharacter,
+
+    /// Input ended inside of a tag, comment or other markup
+    UnexpectedEof,
+
+    /// Tag, text or comment that is not allowed at its place in the document
+    UnexpectedToken,
+
+    /// End tag that does not match open elements, or elements left open at the end of the input,
+    /// so elements had to be closed implicitly
+    MisnestedTag,
+
+    /// Doctype that is invalid or not at the start of the document
+    BadDoctype,
+
+    /// Duplicate attribute, or attributes on an end tag
+    BadAttribute,
+
+    /// Self-closing syntax on an end tag or on an element that is not void
+    BadSelfClosingTag,
+
+    /// Any other problem, see the message
+    Other,
+}
+
+impl HtmlErrorKind {
+    /// Classify message reported by `html5ever`.
+    ///
+    /// `html5ever` reports errors only as text, the prefixes below are the ones of html5ever 0.31.
+    /// Check them against its tree builder and tokenizer when upgrading it.
+    fn from_message(message: &str) -> Self {
+        const MISNESTED_TAG: &[&str] = &[
+            "Unexpected open element",
+            "Unexpected open tag",
+            "No matching tag",
+            "No <p> tag",
+            "No heading tag",
+            "Closing wrong heading tag",
+            "Found special tag",
+            "Formatting element",
+            "Form element",
+            "Bad open element",
+            "Null form element",
+            "expected to close",
+            "</body> with no",
+            "</html> with no",
+            "Nested",
+            "nested",
+        ];
+
+        match message {
+            "Unexpected EOF" => Self::UnexpectedEof,
+            _ if message.starts_with("Saw EOF") => Self::UnexpectedEof,
+            _ if message.starts_with("Bad character") || message.starts_with("Saw ") => {
+                Self::BadCharacter
+            }
+            _ if message.starts_with("Unexpected token")
+                || message.starts_with("Unexpected characters")
+                || message.starts_with("Non-space table text") =>
+            {
+                Self::UnexpectedToken
+            }
+            _ if message.starts_with("Bad DOCTYPE") || message.starts_with("DOCTYPE in") => {
+                Self::BadDoctype
+            }
+            "Duplicate attribute" | "Attributes on an end tag" => Self::BadAttribute,
+            "Unacknowledged self-closing tag" | "Self-closing end tag" => Self::BadSelfClosingTag,
+            _ if MISNESTED_TAG
+                .iter()
+                .any(|prefix| message.starts_with(prefix)) =>
+            {
+                Self::MisnestedTag
+            }
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Problem found while parsing HTML, the document is still parsed as well as possible.
+///
+/// Position is where the parser was when it found the error, e.g. the end of a misnested tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlError {
+    /// Line of the input the error was found on, starting from 1
+    pub line: u64,
+
+    /// Column of the line in characters, starting from 1. Known only with the `error-columns`
+    /// feature
+    pub column: Option<u64>,
+
+    /// Kind of the error
+    pub kind: HtmlErrorKind,
+
+    /// Description of the error reported by `html5ever`
+    pub message: Cow<'static, str>,
+}
+
+impl HtmlError {
+    pub(crate) fn new(line: u64, column: Option<u64>, message: Cow<'static, str>) -> Self {
+        Self {
+            line,
+            column,
+            kind: HtmlErrorKind::from_message(&message),
+            message,
+        }
+    }
+}
+
+impl Display for HtmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.column {
+            Some(column) => write!(f, "{}:{column}: {}", self.line, self.message),
+            None => write!(f, "{}: {}", self.line, self.message),
+        }
+    }
+}
+
+impl Error for HtmlError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "errors")]
+    #[test]
+    fn html5ever_messages_are_classified() {
+        use crate::html::Html;
+        use HtmlErrorKind::*;
+
+        // Malformed inputs with kinds of every error html5ever reports for them, so a change of its
+        // messages shows up here instead of as `Other`.
+        let inputs = [
+            ("<!DOCTYPE html><div", vec![UnexpectedEof]),
+            ("<!DOCTYPE html><!--", vec![UnexpectedEof]),
+            ("<!DOCTYPE html><p>\u{1}</p>", vec![BadCharacter]),
+            ("<!DOCTYPE html><a <b>x</a>", vec![BadCharacter]),
+            ("<!DOCTYPE html><table>x</table>", vec![UnexpectedToken]),
+            ("<p>", vec![UnexpectedToken]),
+            ("<!DOCTYPE foo>", vec![BadDoctype]),
+            ("<!DOCTYPE html><body><!DOCTYPE html>", vec![BadDoctype]),
+            ("<!DOCTYPE html><p a=1 a=2>", vec![BadAttribute]),
+            ("<!DOCTYPE html><p></p a=1>", vec![BadAttribute]),
+            ("<!DOCTYPE html><div/></div>", vec![BadSelfClosingTag]),
+            ("<!DOCTYPE html><b>x</i></b>", vec![MisnestedTag]),
+            ("<!DOCTYPE html><b><p>x</b></p>", vec![MisnestedTag]),
+            ("<!DOCTYPE html><h1>x</h2>", vec![MisnestedTag]),
+            ("<!DOCTYPE html><form><form></form>", vec![MisnestedTag]),
+            ("<!DOCTYPE html><nobr><nobr></nobr>", vec![MisnestedTag]),
+            ("<!DOCTYPE html><div>", vec![MisnestedTag]),
+        ];
+        for (input, kinds) in inputs {
+            let html = Html::parse_document(input);
+            let errors: Vec<_> = html.errors.iter().map(|e| (e.kind, &*e.message)).collect();
+            assert_eq!(
+                errors.iter().map(|&(kind, _)| kind).collect::<Vec<_>>(),
+                kinds,
+                "{input}: {errors:?}"
+            );
+        }
+    }
+}
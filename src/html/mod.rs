@@ -13,7 +13,7 @@
 //! ## Parsing a document
 //!
 //! ```
-//! use scraper::Html;
+//! use wbp::html::Html;
 //!
 //! let html = r#"
 //!     <!DOCTYPE html>
@@ -28,21 +28,21 @@
 //! ## Parsing a fragment
 //!
 //! ```
-//! use scraper::Html;
+//! use wbp::html::Html;
 //! let fragment = Html::parse_fragment("<h1>Hello, <i>world!</i></h1>");
 //! ```
 //!
 //! ## Parsing a selector
 //!
 //! ```
-//! use scraper::Selector;
-//! let selector = Selector::parse("h1.foo").unwrap();
+//! use wbp::selector::SelectorGroup;
+//! let selector = SelectorGroup::parse("h1.foo").unwrap();
 //! ```
 //!
 //! ## Selecting elements
 //!
 //! ```
-//! use scraper::{Html, Selector};
+//! use wbp::{html::Html, selector::SelectorGroup};
 //!
 //! let html = r#"
 //!     <ul>
@@ -53,7 +53,7 @@
 //! "#;
 //!
 //! let fragment = Html::parse_fragment(html);
-//! let selector = Selector::parse("li").unwrap();
+//! let selector = SelectorGroup::parse("li").unwrap();
 //!
 //! for element in fragment.select(&selector) {
 //!     assert_eq!("li", element.value().name());
@@ -63,7 +63,7 @@
 //! ## Selecting descendent elements
 //!
 //! ```
-//! use scraper::{Html, Selector};
+//! use wbp::{html::Html, selector::SelectorGroup};
 //!
 //! let html = r#"
 //!     <ul>
@@ -74,8 +74,8 @@
 //! "#;
 //!
 //! let fragment = Html::parse_fragment(html);
-//! let ul_selector = Selector::parse("ul").unwrap();
-//! let li_selector = Selector::parse("li").unwrap();
+//! let ul_selector = SelectorGroup::parse("ul").unwrap();
+//! let li_selector = SelectorGroup::parse("li").unwrap();
 //!
 //! let ul = fragment.select(&ul_selector).next().unwrap();
 //! for element in ul.select(&li_selector) {
@@ -86,10 +86,10 @@
 //! ## Accessing element attributes
 //!
 //! ```
-//! use scraper::{Html, Selector};
+//! use wbp::{html::Html, selector::SelectorGroup};
 //!
 //! let fragment = Html::parse_fragment(r#"<input name="foo" value="bar">"#);
-//! let selector = Selector::parse(r#"input[name="foo"]"#).unwrap();
+//! let selector = SelectorGroup::parse(r#"input[name="foo"]"#).unwrap();
 //!
 //! let input = fragment.select(&selector).next().unwrap();
 //! assert_eq!(Some("bar"), input.value().attr("value"));
@@ -98,10 +98,10 @@
 //! ## Serializing HTML and inner HTML
 //!
 //! ```
-//! use scraper::{Html, Selector};
+//! use wbp::{html::Html, selector::SelectorGroup};
 //!
 //! let fragment = Html::parse_fragment("<h1>Hello, <i>world!</i></h1>");
-//! let selector = Selector::parse("h1").unwrap();
+//! let selector = SelectorGroup::parse("h1").unwrap();
 //!
 //! let h1 = fragment.select(&selector).next().unwrap();
 //!
@@ -112,10 +112,10 @@
 //! ## Accessing descendent text
 //!
 //! ```
-//! use scraper::{Html, Selector};
+//! use wbp::{html::Html, selector::SelectorGroup};
 //!
 //! let fragment = Html::parse_fragment("<h1>Hello, <i>world!</i></h1>");
-//! let selector = Selector::parse("h1").unwrap();
+//! let selector = SelectorGroup::parse("h1").unwrap();
 //!
 //! let h1 = fragment.select(&selector).next().unwrap();
 //! let text = h1.text().collect::<Vec<_>>();
@@ -142,6 +142,7 @@ pub use tendril_util::StrTendril;
 
 pub mod element_ref;
 pub mod error;
+mod mutation;
 pub mod node;
 pub mod selectable;
 
@@ -156,20 +157,24 @@ pub(crate) mod tendril_util {
     }
 }
 
-#[cfg(feature = "errors")]
 use std::borrow::Cow;
 use std::fmt;
+use std::io::Error;
 use std::iter::FusedIterator;
 
 use ego_tree::iter::Nodes;
-use ego_tree::Tree;
+use ego_tree::{NodeRef, Tree};
+use html5ever::driver::{self, ParseOpts};
+use html5ever::serialize::{serialize, Serialize, SerializeOpts, Serializer, TraversalScope};
 use html5ever::tree_builder::QuirksMode;
-use html5ever::{driver, QualName};
+use html5ever::QualName;
 use html5ever::{local_name, namespace_url, ns};
 use selectors::matching::SelectorCaches;
 use tendril::TendrilSink;
 
 use crate::html::element_ref::ElementNode;
+#[cfg(feature = "errors")]
+use crate::html::error::HtmlError;
 use crate::selector::SelectorGroup;
 
 /// An HTML tree.
@@ -181,8 +186,8 @@ use crate::selector::SelectorGroup;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Html {
     #[cfg(feature = "errors")]
-    /// Parse errors.
-    pub errors: Vec<Cow<'static, str>>,
+    /// Parse errors in the order they were found.
+    pub errors: Vec<HtmlError>,
 
     /// The quirks mode.
     pub quirks_mode: QuirksMode,
@@ -214,16 +219,16 @@ impl Html {
 
     /// Parses a string of HTML as a document.
     ///
-    /// This is a convenience method for the following:
+    /// This is a convenience method for the following, except that with the `errors` feature
+    /// every parse error described by the specification is reported:
     ///
     /// ```
     /// # extern crate html5ever;
-    /// # extern crate scraper;
     /// # extern crate tendril;
     /// # fn main() {
     /// # let document = "";
     /// use html5ever::driver::{self, ParseOpts};
-    /// use scraper::{Html, HtmlTreeSink};
+    /// use wbp::html::{Html, HtmlTreeSink};
     /// use tendril::TendrilSink;
     ///
     /// let parser = driver::parse_document(HtmlTreeSink::new(Html::new_document()), ParseOpts::default());
@@ -231,23 +236,23 @@ impl Html {
     /// # }
     /// ```
     pub fn parse_document(document: &str) -> Self {
-        let parser =
-            driver::parse_document(HtmlTreeSink::new(Self::new_document()), Default::default());
-        parser.one(document)
+        let parser = driver::parse_document(HtmlTreeSink::new(Self::new_document()), parse_opts());
+        parse(parser, document)
     }
 
     /// Parses a string of HTML as a fragment.
     pub fn parse_fragment(fragment: &str) -> Self {
         let parser = driver::parse_fragment(
             HtmlTreeSink::new(Self::new_fragment()),
-            Default::default(),
+            parse_opts(),
             QualName::new(None, ns!(html), local_name!("body")),
             Vec::new(),
         );
-        parser.one(fragment)
+        parse(parser, fragment)
     }
 
-    /// Returns an iterator over elements matching a selector.
+    /// Returns an iterator over elements matching a selector. Detached elements, e.g. removed
+    /// ones, are skipped.
     pub fn select<'a, 'b>(&'a self, selector: &'b SelectorGroup) -> Select<'a, 'b, Node> {
         Select {
             inner: self.tree.nodes(),
@@ -267,17 +272,54 @@ impl Html {
         ElementRef::wrap(root_node).unwrap()
     }
 
-    // /// Serialize entire document into HTML.
-    // pub fn html(&self) -> String {
-    //     let opts = SerializeOpts {
-    //         scripting_enabled: false, // It's not clear what this does.
-    //         traversal_scope: serialize::TraversalScope::IncludeNode,
-    //         create_missing_parent: false,
-    //     };
-    //     let mut buf = Vec::new();
-    //     serialize(&mut buf, self, opts).unwrap();
-    //     String::from_utf8(buf).unwrap()
-    // }
+    /// Serialize entire document into HTML.
+    pub fn html(&self) -> String {
+        let opts = SerializeOpts {
+            scripting_enabled: false, // It's not clear what this does.
+            traversal_scope: TraversalScope::IncludeNode,
+            create_missing_parent: false,
+        };
+        let mut buf = Vec::new();
+        serialize(&mut buf, self, opts).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+}
+
+/// Options of the `html5ever` parser.
+///
+/// Exact errors are reported only with the `errors` feature, since they cost some performance.
+fn parse_opts() -> ParseOpts {
+    let mut opts = ParseOpts::default();
+    opts.tokenizer.exact_errors = cfg!(feature = "errors");
+    opts.tree_builder.exact_errors = cfg!(feature = "errors");
+    opts
+}
+
+/// Feed whole `source` to the parser.
+///
+/// `html5ever` does not tell the column it is at, so with the `error-columns` feature input is fed
+/// character by character to know the column of every parse error. This is much slower.
+#[cfg(not(feature = "error-columns"))]
+fn parse(parser: driver::Parser<HtmlTreeSink>, source: &str) -> Html {
+    parser.one(source)
+}
+
+/// Feed whole `source` to the parser.
+///
+/// `html5ever` does not tell the column it is at, so with the `error-columns` feature input is fed
+/// character by character to know the column of every parse error. This is much slower.
+#[cfg(feature = "error-columns")]
+fn parse(mut parser: driver::Parser<HtmlTreeSink>, source: &str) -> Html {
+    let mut column = 0;
+    for (start, c) in source.char_indices() {
+        column += 1;
+        parser.tokenizer.sink.sink.column.set(Some(column));
+        parser.process(source[start..start + c.len_utf8()].into());
+        if c == '\n' {
+            column = 0;
+        }
+    }
+    parser.finish()
 }
 
 /// Iterator over elements matching a selector.
@@ -313,7 +355,7 @@ impl<'a, E: ElementNode + Clone> Iterator for Select<'a, '_, E> {
     fn next(&mut self) -> Option<ElementRef<'a, E>> {
         for node in self.inner.by_ref() {
             if let Some(element) = ElementRef::wrap(node) {
-                if element.parent().is_some()
+                if is_attached(node)
                     && self
                         .selector
                         .matches_with_scope_and_cache(&element, None, &mut self.caches)
@@ -336,7 +378,7 @@ impl<E: ElementNode + Clone> DoubleEndedIterator for Select<'_, '_, E> {
     fn next_back(&mut self) -> Option<Self::Item> {
         for node in self.inner.by_ref().rev() {
             if let Some(element) = ElementRef::wrap(node) {
-                if element.parent().is_some()
+                if is_attached(node)
                     && self
                         .selector
                         .matches_with_scope_and_cache(&element, None, &mut self.caches)
@@ -351,22 +393,41 @@ impl<E: ElementNode + Clone> DoubleEndedIterator for Select<'_, '_, E> {
 
 impl<E: ElementNode + Clone> FusedIterator for Select<'_, '_, E> {}
 
+/// Whether `node` is in the document, i.e. the root is one of its ancestors. Nodes created or
+/// removed by mutations stay in the arena of the tree, but are not attached to the root.
+fn is_attached<E>(node: NodeRef<E>) -> bool {
+    let root = node.tree().root().id();
+    node.ancestors().any(|ancestor| ancestor.id() == root)
+}
+
 use crate::html::node::{Comment, Doctype, ProcessingInstruction, Text};
 use crate::html::tendril_util::make as make_tendril;
 use ego_tree::NodeId;
 use html5ever::expanded_name;
 use html5ever::tree_builder::{ElementFlags, NodeOrText, TreeSink};
 use html5ever::Attribute;
-use std::cell::{Ref, RefCell};
+use std::cell::{Cell, Ref, RefCell};
 
 /// Wraps `Html` instances as sinks to drive parsing
 #[derive(Debug)]
-pub struct HtmlTreeSink(pub RefCell<Html>);
+pub struct HtmlTreeSink {
+    /// Document being built.
+    pub html: RefCell<Html>,
+    /// Line of the input tokenizer is at, used as position of parse errors.
+    line: Cell<u64>,
+    /// Column of the last character fed to the parser, `None` if it is not fed character by
+    /// character.
+    column: Cell<Option<u64>>,
+}
 
 impl HtmlTreeSink {
     /// Wrap a `Html`instance as a sink to drive parsing
     pub fn new(html: Html) -> Self {
-        Self(RefCell::new(html))
+        Self {
+            html: RefCell::new(html),
+            line: Cell::new(1),
+            column: Cell::new(None),
+        }
     }
 }
 
@@ -377,25 +438,33 @@ impl TreeSink for HtmlTreeSink {
     type ElemName<'a> = Ref<'a, QualName>;
 
     fn finish(self) -> Html {
-        self.0.into_inner()
+        self.html.into_inner()
     }
 
     // Signal a parse error.
     fn parse_error(&self, msg: Cow<'static, str>) {
         #[cfg(feature = "errors")]
-        self.0.borrow_mut().errors.push(msg);
+        self.html
+            .borrow_mut()
+            .errors
+            .push(HtmlError::new(self.line.get(), self.column.get(), msg));
         #[cfg(not(feature = "errors"))]
         let _ = msg;
     }
 
+    // Remember line of the input for the following parse errors.
+    fn set_current_line(&self, line_number: u64) {
+        self.line.set(line_number);
+    }
+
     // Set the document's quirks mode.
     fn set_quirks_mode(&self, mode: QuirksMode) {
-        self.0.borrow_mut().quirks_mode = mode;
+        self.html.borrow_mut().quirks_mode = mode;
     }
 
     // Get a handle to the Document node.
     fn get_document(&self) -> Self::Handle {
-        self.0.borrow().tree.root().id()
+        self.html.borrow().tree.root().id()
     }
 
     // Do two handles refer to the same node?
@@ -407,7 +476,7 @@ impl TreeSink for HtmlTreeSink {
     //
     // Should never be called on a non-element node; feel free to panic!.
     fn elem_name<'a>(&'a self, target: &Self::Handle) -> Ref<'a, QualName> {
-        Ref::map(self.0.borrow(), |this| {
+        Ref::map(self.html.borrow(), |this| {
             &this
                 .tree
                 .get(*target)
@@ -432,7 +501,7 @@ impl TreeSink for HtmlTreeSink {
     ) -> Self::Handle {
         let fragment = name.expanded() == expanded_name!(html "template");
 
-        let mut this = self.0.borrow_mut();
+        let mut this = self.html.borrow_mut();
         let mut node = this.tree.orphan(Node::Element(Element::new(name, attrs)));
 
         if fragment {
@@ -444,7 +513,7 @@ impl TreeSink for HtmlTreeSink {
 
     // Create a comment node.
     fn create_comment(&self, text: StrTendril) -> Self::Handle {
-        self.0
+        self.html
             .borrow_mut()
             .tree
             .orphan(Node::Comment(Comment {
@@ -468,7 +537,7 @@ impl TreeSink for HtmlTreeSink {
             public_id,
             system_id,
         };
-        self.0
+        self.html
             .borrow_mut()
             .tree
             .root_mut()
@@ -480,7 +549,7 @@ impl TreeSink for HtmlTreeSink {
     //
     // The child node will not already have a parent.
     fn append(&self, parent: &Self::Handle, child: NodeOrText<Self::Handle>) {
-        let mut this = self.0.borrow_mut();
+        let mut this = self.html.borrow_mut();
         let mut parent = this.tree.get_mut(*parent).unwrap();
 
         match child {
@@ -515,7 +584,7 @@ impl TreeSink for HtmlTreeSink {
     //
     // NB: new_node may have an old parent, from which it should be removed.
     fn append_before_sibling(&self, sibling: &Self::Handle, new_node: NodeOrText<Self::Handle>) {
-        let mut this = self.0.borrow_mut();
+        let mut this = self.html.borrow_mut();
 
         if let NodeOrText::AppendNode(id) = new_node {
             this.tree.get_mut(id).unwrap().detach();
@@ -549,12 +618,17 @@ impl TreeSink for HtmlTreeSink {
 
     // Detach the given node from its parent.
     fn remove_from_parent(&self, target: &Self::Handle) {
-        self.0.borrow_mut().tree.get_mut(*target).unwrap().detach();
+        self.html
+            .borrow_mut()
+            .tree
+            .get_mut(*target)
+            .unwrap()
+            .detach();
     }
 
     // Remove all the children from node and append them to new_parent.
     fn reparent_children(&self, node: &Self::Handle, new_parent: &Self::Handle) {
-        self.0
+        self.html
             .borrow_mut()
             .tree
             .get_mut(*new_parent)
@@ -565,7 +639,7 @@ impl TreeSink for HtmlTreeSink {
     // Add each attribute to the given element, if no attribute with that name already exists. The
     // tree builder promises this will never be called with something else than an element.
     fn add_attrs_if_missing(&self, target: &Self::Handle, attrs: Vec<Attribute>) {
-        let mut this = self.0.borrow_mut();
+        let mut this = self.html.borrow_mut();
         let mut node = this.tree.get_mut(*target).unwrap();
         let element = match *node.value() {
             Node::Element(ref mut e) => e,
@@ -589,7 +663,7 @@ impl TreeSink for HtmlTreeSink {
     // The tree builder promises this will never be called with something else than a template
     // element.
     fn get_template_contents(&self, target: &Self::Handle) -> Self::Handle {
-        self.0
+        self.html
             .borrow()
             .tree
             .get(*target)
@@ -606,7 +680,7 @@ impl TreeSink for HtmlTreeSink {
     fn create_pi(&self, target: StrTendril, data: StrTendril) -> Self::Handle {
         let target = make_tendril(target);
         let data = make_tendril(data);
-        self.0
+        self.html
             .borrow_mut()
             .tree
             .orphan(Node::ProcessingInstruction(ProcessingInstruction {
@@ -623,7 +697,7 @@ impl TreeSink for HtmlTreeSink {
         child: NodeOrText<Self::Handle>,
     ) {
         let has_parent = self
-            .0
+            .html
             .borrow()
             .tree
             .get(*element)
@@ -639,39 +713,28 @@ impl TreeSink for HtmlTreeSink {
     }
 }
 
-// use std::io::Error;
-
-// use html5ever::serialize::{Serialize, Serializer, TraversalScope};
-
-// use crate::html::Html;
-
-// impl Serialize for Html {
-//     fn serialize<S: Serializer>(
-//         &self,
-//         serializer: &mut S,
-//         traversal_scope: TraversalScope,
-//     ) -> Result<(), Error> {
-//         crate::html::node::serializable::serialize(self.tree.root(), serializer, traversal_scope)
-//     }
-// }
-
-// #[cfg(test)]
-// mod tests {
-//     use crate::html::Html;
-
-//     #[test]
-//     fn test_serialize() {
-//         let src = r#"<!DOCTYPE html><html lang="en"><head><meta charset="utf-8"></head><body><p>Hello world!</p></body></html>"#;
-//         let html = Html::parse_document(src);
-//         assert_eq!(html.html(), src);
-//     }
-// }
+impl Serialize for Html {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: &mut S,
+        traversal_scope: TraversalScope,
+    ) -> Result<(), Error> {
+        node::serializable::serialize(self.tree.root(), serializer, traversal_scope)
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use crate::html::Html;
     use crate::selector::SelectorGroup;
 
+    #[test]
+    fn test_serialize() {
+        let src = r#"<!DOCTYPE html><html lang="en"><head><meta charset="utf-8"></head><body><p>Hello world!</p></body></html>"#;
+        let html = Html::parse_document(src);
+        assert_eq!(html.html(), src);
+    }
+
     #[test]
     fn tag_with_newline() {
         let selector = SelectorGroup::parse("a").unwrap();
@@ -725,43 +788,43 @@ mod tests {
             .select(&SelectorGroup::parse("a").unwrap())
             .next()
             .unwrap();
-        // assert_eq!(href.inner_html(), "1");
+        assert_eq!(href.inner_html(), "1");
         assert_eq!(href.value().attr("href").unwrap(), "http://github.com");
     }
 
-    // #[test]
-    // fn root_element_document_doctype() {
-    //     let html = Html::parse_document("<!DOCTYPE html>\n<title>abc</title>");
-    //     let root_ref = html.root_element();
-    //     let title = root_ref
-    //         .select(&Selector::parse("title").unwrap())
-    //         .next()
-    //         .unwrap();
-    //     assert_eq!(title.inner_html(), "abc");
-    // }
+    #[test]
+    fn root_element_document_doctype() {
+        let html = Html::parse_document("<!DOCTYPE html>\n<title>abc</title>");
+        let root_ref = html.root_element();
+        let title = root_ref
+            .select(&SelectorGroup::parse("title").unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(title.inner_html(), "abc");
+    }
 
-    // #[test]
-    // fn root_element_document_comment() {
-    //     let html = Html::parse_document("<!-- comment --><title>abc</title>");
-    //     let root_ref = html.root_element();
-    //     let title = root_ref
-    //         .select(&Selector::parse("title").unwrap())
-    //         .next()
-    //         .unwrap();
-    //     assert_eq!(title.inner_html(), "abc");
-    // }
+    #[test]
+    fn root_element_document_comment() {
+        let html = Html::parse_document("<!-- comment --><title>abc</title>");
+        let root_ref = html.root_element();
+        let title = root_ref
+            .select(&SelectorGroup::parse("title").unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(title.inner_html(), "abc");
+    }
 
-    // #[test]
-    // fn select_is_reversible() {
-    //     let html = Html::parse_document("<p>element1</p><p>element2</p><p>element3</p>");
-    //     let selector = Selector::parse("p").unwrap();
-    //     let result: Vec<_> = html
-    //         .select(&selector)
-    //         .rev()
-    //         .map(|e| e.inner_html())
-    //         .collect();
-    //     assert_eq!(result, vec!["element3", "element2", "element1"]);
-    // }
+    #[test]
+    fn select_is_reversible() {
+        let html = Html::parse_document("<p>element1</p><p>element2</p><p>element3</p>");
+        let selector = SelectorGroup::parse("p").unwrap();
+        let result: Vec<_> = html
+            .select(&selector)
+            .rev()
+            .map(|e| e.inner_html())
+            .collect();
+        assert_eq!(result, vec!["element3", "element2", "element1"]);
+    }
 
     #[test]
     fn select_has_a_size_hint() {
@@ -771,4 +834,36 @@ mod tests {
         assert_eq!(lower, 0);
         assert_eq!(upper, Some(10));
     }
+
+    #[cfg(feature = "errors")]
+    #[test]
+    fn parse_errors_have_positions_and_kinds() {
+        use crate::html::error::HtmlErrorKind;
+
+        let html = Html::parse_document("<!DOCTYPE html>\n<p a=1 a=2>\n<b>x</i></b>\n<p>\u{1}");
+        let errors: Vec<_> = html
+            .errors
+            .iter()
+            .map(|e| (e.line, e.column, e.kind))
+            .collect();
+        // Columns are known only when input is fed character by character.
+        let column = |column| cfg!(feature = "error-columns").then_some(column);
+        assert_eq!(
+            errors,
+            [
+                (2, column(11), HtmlErrorKind::BadAttribute),
+                (3, column(8), HtmlErrorKind::MisnestedTag),
+                (4, column(4), HtmlErrorKind::BadCharacter),
+            ]
+        );
+        let position = if cfg!(feature = "error-columns") {
+            "2:11"
+        } else {
+            "2"
+        };
+        assert_eq!(
+            html.errors[0].to_string(),
+            format!("{position}: Duplicate attribute")
+        );
+    }
 }
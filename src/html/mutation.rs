@@ -0,0 +1,272 @@
+//! Safe modification of a parsed tree.
+//!
+//! Nodes are addressed by their [`NodeId`]. New nodes are created detached from the tree and
+//! become visible once inserted under a parent. Removed nodes are only detached, their ids stay
+//! valid, so they can be inserted again, e.g. to move a node to another place.
+
+use ego_tree::{NodeId, NodeMut};
+use html5ever::{ns, LocalName, QualName};
+
+use crate::html::error::MutationError;
+use crate::html::node::{Element, Text};
+use crate::html::{Html, Node, StrTendril};
+
+impl Html {
+    /// Creates a detached HTML element without attributes.
+    pub fn create_element(&mut self, name: &str) -> NodeId {
+        let name = QualName::new(None, ns!(html), LocalName::from(name));
+        self.tree
+            .orphan(Node::Element(Element::new(name, Vec::new())))
+            .id()
+    }
+
+    /// Creates a detached text node.
+    pub fn create_text(&mut self, text: &str) -> NodeId {
+        self.tree
+            .orphan(Node::Text(Text {
+                text: StrTendril::from(text),
+            }))
+            .id()
+    }
+
+    /// Inserts `child` as the last child of `parent`, detaching it from its current place first.
+    pub fn append_child(&mut self, parent: NodeId, child: NodeId) -> Result<(), MutationError> {
+        self.check_insertion(parent, child)?;
+        self.node_mut(parent)?.append_id(child);
+        Ok(())
+    }
+
+    /// Inserts `child` right before `reference`, detaching it from its current place first.
+    pub fn insert_before(&mut self, reference: NodeId, child: NodeId) -> Result<(), MutationError> {
+        let parent = self
+            .tree
+            .get(reference)
+            .ok_or(MutationError::NotFound)?
+            .parent()
+            .ok_or(MutationError::HierarchyRequest)?
+            .id();
+        self.check_insertion(parent, child)?;
+        if reference != child {
+            self.node_mut(reference)?.insert_id_before(child);
+        }
+        Ok(())
+    }
+
+    /// Detaches `node` together with its descendants from the tree.
+    pub fn remove(&mut self, node: NodeId) -> Result<(), MutationError> {
+        if node == self.tree.root().id() {
+            return Err(MutationError::HierarchyRequest);
+        }
+        self.node_mut(node)?.detach();
+        Ok(())
+    }
+
+    /// Sets the value of attribute `name` of `element`, adding the attribute if it is missing.
+    pub fn set_attr(
+        &mut self,
+        element: NodeId,
+        name: &str,
+        value: &str,
+    ) -> Result<(), MutationError> {
+        self.with_element(element, |element| element.set_attr(name, value))
+    }
+
+    /// Removes attribute `name` of `element`, returning its value if it was present.
+    pub fn remove_attr(
+        &mut self,
+        element: NodeId,
+        name: &str,
+    ) -> Result<Option<StrTendril>, MutationError> {
+        self.with_element(element, |element| element.remove_attr(name))
+    }
+
+    /// Replaces text of a text or comment node. For other nodes, replaces all of their children
+    /// with a single text node, or with nothing if `text` is empty.
+    pub fn set_text_content(&mut self, node: NodeId, text: &str) -> Result<(), MutationError> {
+        let mut node_mut = self.node_mut(node)?;
+        match node_mut.value() {
+            Node::Text(t) => t.text = StrTendril::from(text),
+            Node::Comment(c) => c.comment = StrTendril::from(text),
+            Node::ProcessingInstruction(pi) => pi.data = StrTendril::from(text),
+            Node::Doctype(_) => {}
+            Node::Document | Node::Fragment | Node::Element(_) => {
+                while let Some(mut child) = node_mut.first_child() {
+                    child.detach();
+                }
+                if !text.is_empty() {
+                    let text = self.create_text(text);
+                    self.append_child(node, text)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn node_mut(&mut self, node: NodeId) -> Result<NodeMut<'_, Node>, MutationError> {
+        self.tree.get_mut(node).ok_or(MutationError::NotFound)
+    }
+
+    fn with_element<R>(
+        &mut self,
+        node: NodeId,
+        f: impl FnOnce(&mut Element) -> R,
+    ) -> Result<R, MutationError> {
+        match self.node_mut(node)?.value() {
+            Node::Element(element) => Ok(f(element)),
+            _ => Err(MutationError::NotAnElement),
+        }
+    }
+
+    /// Checks that `child` may become a child of `parent`.
+    fn check_insertion(&self, parent: NodeId, child: NodeId) -> Result<(), MutationError> {
+        let parent = self.tree.get(parent).ok_or(MutationError::NotFound)?;
+        let child = self.tree.get(child).ok_or(MutationError::NotFound)?;
+
+        let can_have_children = matches!(
+            parent.value(),
+            Node::Document | Node::Fragment | Node::Element(_)
+        );
+        let is_root = matches!(child.value(), Node::Document | Node::Fragment);
+        // Node can't become a descendant of itself.
+        let is_ancestor = parent.ancestors().chain([parent]).any(|node| node == child);
+
+        if !can_have_children || is_root || is_ancestor {
+            return Err(MutationError::HierarchyRequest);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::html::error::MutationError;
+    use crate::html::{CaseSensitivity, Html};
+    use crate::selector::SelectorGroup;
+
+    fn find(html: &Html, selector: &str) -> ego_tree::NodeId {
+        html.select(&SelectorGroup::parse(selector).unwrap())
+            .next()
+            .unwrap()
+            .id()
+    }
+
+    #[test]
+    fn build_and_rearrange_nodes() {
+        let mut html = Html::parse_fragment("<ul><li>b</li></ul>");
+        let ul = find(&html, "ul");
+        let b = find(&html, "li");
+
+        let a = html.create_element("li");
+        html.set_text_content(a, "a").unwrap();
+        html.insert_before(b, a).unwrap();
+        let c = html.create_element("li");
+        let c_text = html.create_text("c");
+        html.append_child(c, c_text).unwrap();
+        html.append_child(ul, c).unwrap();
+        assert_eq!(
+            html.root_element().inner_html(),
+            "<ul><li>a</li><li>b</li><li>c</li></ul>"
+        );
+
+        // Inserting an attached node moves it.
+        html.append_child(ul, a).unwrap();
+        html.remove(b).unwrap();
+        assert_eq!(
+            html.root_element().inner_html(),
+            "<ul><li>c</li><li>a</li></ul>"
+        );
+    }
+
+    #[test]
+    fn detached_nodes_are_not_selected() {
+        let mut html = Html::parse_fragment("<div><p>removed</p></div><p>kept</p>");
+        let div = find(&html, "div");
+        html.remove(div).unwrap();
+        let orphan = html.create_element("section");
+        let orphan_p = html.create_element("p");
+        html.append_child(orphan, orphan_p).unwrap();
+
+        let selector = SelectorGroup::parse("div, section, p").unwrap();
+        let texts: Vec<_> = html.select(&selector).map(|e| e.inner_html()).collect();
+        assert_eq!(texts, ["kept"]);
+    }
+
+    #[test]
+    fn invalid_insertions_are_rejected() {
+        let mut html = Html::parse_fragment("<div><p>text</p></div>");
+        let div = find(&html, "div");
+        let p = find(&html, "p");
+        let text = html.tree.get(p).unwrap().first_child().unwrap().id();
+        let root = html.tree.root().id();
+
+        assert_eq!(
+            html.append_child(p, div),
+            Err(MutationError::HierarchyRequest)
+        );
+        assert_eq!(
+            html.append_child(text, div),
+            Err(MutationError::HierarchyRequest)
+        );
+        assert_eq!(
+            html.insert_before(root, div),
+            Err(MutationError::HierarchyRequest)
+        );
+        assert_eq!(html.remove(root), Err(MutationError::HierarchyRequest));
+        assert_eq!(
+            html.set_attr(text, "id", "x"),
+            Err(MutationError::NotAnElement)
+        );
+        assert_eq!(html.root_element().inner_html(), "<div><p>text</p></div>");
+    }
+
+    #[test]
+    fn attribute_changes_update_id_and_classes() {
+        let mut html = Html::parse_fragment(r#"<p id="a" class="x">text</p>"#);
+        let p = find(&html, "p");
+        let element = html.tree.get(p).unwrap().value().as_element().unwrap();
+        // Fill caches.
+        assert_eq!(element.id(), Some("a"));
+        assert!(element.has_class("x", CaseSensitivity::CaseSensitive));
+
+        html.set_attr(p, "id", "b").unwrap();
+        html.set_attr(p, "class", "y z").unwrap();
+        html.set_attr(p, "title", "t").unwrap();
+        assert_eq!(find(&html, "#b.y.z"), p);
+
+        assert_eq!(html.remove_attr(p, "id").unwrap().as_deref(), Some("b"));
+        assert_eq!(html.remove_attr(p, "id").unwrap(), None);
+        let element = html.tree.get(p).unwrap().value().as_element().unwrap();
+        assert_eq!(element.id(), None);
+        assert_eq!(
+            html.root_element().inner_html(),
+            r#"<p class="y z" title="t">text</p>"#
+        );
+    }
+
+    #[test]
+    fn attribute_changes_update_disabled_and_checked_state() {
+        let mut html = Html::parse_fragment(r#"<input type="checkbox"><option>o</option>"#);
+        let input = find(&html, "input");
+        let option = find(&html, "option");
+        let count = |html: &Html, selector| {
+            html.select(&SelectorGroup::parse(selector).unwrap())
+                .count()
+        };
+        assert_eq!(count(&html, ":disabled, :checked"), 0);
+
+        html.set_attr(input, "disabled", "").unwrap();
+        html.set_attr(input, "checked", "").unwrap();
+        html.set_attr(option, "selected", "").unwrap();
+        assert_eq!(find(&html, "input:disabled:checked"), input);
+        assert_eq!(find(&html, "option:checked"), option);
+        let element = html.tree.get(input).unwrap().value().as_element().unwrap();
+        assert!(!element.is_focusable());
+
+        html.remove_attr(input, "disabled").unwrap();
+        html.set_attr(input, "type", "text").unwrap();
+        assert_eq!(find(&html, "input:enabled"), input);
+        assert_eq!(count(&html, "input:checked, input:disabled"), 0);
+        let element = html.tree.get(input).unwrap().value().as_element().unwrap();
+        assert!(element.is_focusable());
+    }
+}
@@ -99,6 +99,14 @@ impl Node {
         }
     }
 
+    /// Returns self as a mutable element.
+    pub fn as_element_mut(&mut self) -> Option<&mut Element> {
+        match *self {
+            Node::Element(ref mut e) => Some(e),
+            _ => None,
+        }
+    }
+
     /// Returns self as an element.
     pub fn as_processing_instruction(&self) -> Option<&ProcessingInstruction> {
         match *self {
@@ -224,6 +232,34 @@ pub struct Element {
     id: OnceCell<Option<StrTendril>>,
 
     classes: OnceCell<Box<[LocalName]>>,
+
+    /// The dynamic state matched by state pseudo-classes.
+    pub state: ElementState,
+}
+
+/// Dynamic state of an element, matched by pseudo-classes like `:hover` or `:checked`.
+///
+/// State changes as the user interacts with the page, only `checked` and `disabled` follow
+/// attributes: they are reset whenever `checked`, `selected`, `type` or `disabled` changes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ElementState {
+    /// Pointer is over the element or one of its descendants.
+    pub hover: bool,
+
+    /// Element or one of its descendants is being pressed.
+    pub active: bool,
+
+    /// Element receives keyboard input.
+    pub focus: bool,
+
+    /// Link target of the element was visited.
+    pub visited: bool,
+
+    /// Checkbox or radio button is checked, or option is selected.
+    pub checked: bool,
+
+    /// Form control is disabled.
+    pub disabled: bool,
 }
 
 impl Element {
@@ -237,12 +273,16 @@ impl Element {
 
         attrs.sort_unstable_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
 
-        Element {
+        let mut element = Element {
             attrs,
             name,
             id: OnceCell::new(),
             classes: OnceCell::new(),
-        }
+            state: ElementState::default(),
+        };
+        element.state.checked = element.default_checked();
+        element.state.disabled = element.default_disabled();
+        element
     }
 
     /// Returns the element name.
@@ -305,6 +345,89 @@ impl Element {
             inner: self.attrs.iter(),
         }
     }
+
+    /// Sets the value of an attribute, adding it if it is missing.
+    pub fn set_attr(&mut self, attr: &str, value: &str) {
+        let qualname = QualName::new(None, ns!(), LocalName::from(attr));
+        let value = StrTendril::from(value);
+
+        match self.attrs.binary_search_by(|attr| attr.0.cmp(&qualname)) {
+            Ok(idx) => self.attrs[idx].1 = value,
+            Err(idx) => self.attrs.insert(idx, (qualname, value)),
+        }
+        self.invalidate_caches(attr);
+    }
+
+    /// Removes an attribute, returning its value if it was present.
+    pub fn remove_attr(&mut self, attr: &str) -> Option<StrTendril> {
+        let qualname = QualName::new(None, ns!(), LocalName::from(attr));
+
+        let idx = self
+            .attrs
+            .binary_search_by(|attr| attr.0.cmp(&qualname))
+            .ok()?;
+        let (_, value) = self.attrs.remove(idx);
+        self.invalidate_caches(attr);
+        Some(value)
+    }
+
+    /// Returns true if the element is a link, i.e. an `a`, `area` or `link` element with `href`.
+    pub fn is_link(&self) -> bool {
+        matches!(self.name(), "a" | "area" | "link") && self.attr("href").is_some()
+    }
+
+    /// Returns true if the element is a checkbox or a radio button.
+    pub fn is_checkable(&self) -> bool {
+        self.name() == "input"
+            && self.attr("type").is_some_and(|ty| {
+                ty.eq_ignore_ascii_case("checkbox") || ty.eq_ignore_ascii_case("radio")
+            })
+    }
+
+    /// Returns true if the element is a form control that can be disabled.
+    pub fn is_form_control(&self) -> bool {
+        matches!(
+            self.name(),
+            "button" | "input" | "select" | "textarea" | "optgroup" | "option" | "fieldset"
+        )
+    }
+
+    /// Returns true if the element can receive keyboard focus.
+    pub fn is_focusable(&self) -> bool {
+        let focusable = match self.name() {
+            "a" | "area" => self.attr("href").is_some(),
+            "input" => !self
+                .attr("type")
+                .is_some_and(|ty| ty.eq_ignore_ascii_case("hidden")),
+            "button" | "select" | "textarea" => true,
+            _ => false,
+        };
+        (focusable || self.attr("tabindex").is_some()) && !self.state.disabled
+    }
+
+    /// Checkedness given by attributes, before the user toggles the element.
+    fn default_checked(&self) -> bool {
+        match self.name() {
+            "input" => self.is_checkable() && self.attr("checked").is_some(),
+            "option" => self.attr("selected").is_some(),
+            _ => false,
+        }
+    }
+
+    fn default_disabled(&self) -> bool {
+        self.is_form_control() && self.attr("disabled").is_some()
+    }
+
+    /// Drop cached values and reset state derived from attribute `attr`.
+    fn invalidate_caches(&mut self, attr: &str) {
+        match attr {
+            "id" => self.id = OnceCell::new(),
+            "class" => self.classes = OnceCell::new(),
+            "checked" | "selected" | "type" => self.state.checked = self.default_checked(),
+            "disabled" => self.state.disabled = self.default_disabled(),
+            _ => {}
+        }
+    }
 }
 
 /// Iterator over classes.
@@ -367,4 +490,4 @@ impl Deref for ProcessingInstruction {
     }
 }
 
-// pub(crate) mod serializable;
+pub(crate) mod serializable;
@@ -9,11 +9,12 @@ use crate::selector::SelectorGroup;
 /// The mainly enables writing helper functions which are generic over [`Html`] and [`ElementRef`], e.g.
 ///
 /// ```
-/// use scraper::{selectable::Selectable, selector::Selector};
+/// use wbp::html::{selectable::Selectable, Node};
+/// use wbp::selector::SelectorGroup;
 ///
-/// fn text_of_first_match<'a, S>(selectable: S, selector: &Selector) -> Option<String>
+/// fn text_of_first_match<'a, S>(selectable: S, selector: &SelectorGroup) -> Option<String>
 /// where
-///     S: Selectable<'a>,
+///     S: Selectable<'a, Node>,
 /// {
 ///     selectable.select(selector).next().map(|element| element.text().collect())
 /// }
@@ -1,4 +1,4 @@
-//! Basic CSS block layout.
+//! Basic CSS block and inline layout.
 use crate::{
     css::{
         props::*,
@@ -7,8 +7,11 @@ use crate::{
             PaddingWidth as PaddingWidthV,
         },
     },
+    html::Node,
     style::StyledNode,
+    text::{self, FontMetrics, ShapedWord, TextRun, TextStyle},
 };
+use std::collections::HashMap;
 use std::default::Default;
 
 pub use self::BoxType::{AnonymousBlock, BlockNode, InlineNode};
@@ -49,6 +52,11 @@ pub struct EdgeSizes {
 pub struct LayoutBox {
     pub dimensions: Dimensions,
     pub box_type: BoxType,
+    /// Areas occupied by an inline box, one per line box it spans. An anonymous block whose first
+    /// line is styled by `::first-line` has the area of the content on that line.
+    pub fragments: Vec<Rect>,
+    /// Glyphs of a text box, one run per line box it spans.
+    pub text_runs: Vec<TextRun>,
 }
 
 type LayoutTree = Tree<LayoutBox>;
@@ -65,6 +73,8 @@ impl LayoutBox {
         LayoutBox {
             box_type,
             dimensions: Default::default(), // initially set all fields to 0.0
+            fragments: Vec::new(),
+            text_runs: Vec::new(),
         }
     }
 
@@ -81,20 +91,26 @@ pub fn layout_tree(
     root_style_node: NodeRef<'_, StyledNode>,
     mut containing_block: Dimensions,
 ) -> LayoutTree {
-    // The layout algorithm expects the container height to start at 0.
-    // TODO: Save the initial containing block height, for calculating percent heights.
+    // The layout algorithm expects the container height to start at 0, but height of the initial
+    // containing block is definite and percent heights of its children refer to it.
+    let containing_block_height = containing_block.content.height;
     containing_block.content.height = 0.0;
 
     // TODO: Change to Tree::with_capacity.
-    let mut layout_tree = Tree::new(LayoutBox::new(match root_style_node.value().display() {
+    let display = root_style_node.value().display();
+    let mut layout_tree = Tree::new(LayoutBox::new(match display {
         Display::Block => BlockNode(root_style_node.value().clone()),
-        Display::Inline => InlineNode(root_style_node.value().clone()),
-        Display::None => panic!("Root node has display: none."),
-        dt => panic!("Unhandeled display type {:#?}", dt),
+        // Nothing is rendered, an empty anonymous block takes place of the root.
+        Display::None => AnonymousBlock,
+        _ => InlineNode(root_style_node.value().clone()),
     }));
 
-    build_layout_tree(layout_tree.root_mut(), root_style_node);
-    layout_tree.root_mut().layout(containing_block);
+    if display != Display::None {
+        build_layout_tree(layout_tree.root_mut(), root_style_node);
+    }
+    layout_tree
+        .root_mut()
+        .layout(containing_block, Some(containing_block_height));
     layout_tree
 }
 
@@ -126,10 +142,22 @@ fn build_layout_tree<'a>(
 /// Ad-hoc trait to extend `NodeMut<'_, LayoutBox>` type.
 pub trait Layoutable {
     /// Lay out a box and its descendants.
-    fn layout(&mut self, containing_block: Dimensions);
+    ///
+    /// `containing_block_height` is the height of the containing block if it is known before its
+    /// content is laid out, percent heights are resolved against it.
+    fn layout(&mut self, containing_block: Dimensions, containing_block_height: Option<f32>);
 
     /// Lay out a block-level element and its descendants.
-    fn layout_block(&mut self, containing_block: Dimensions);
+    fn layout_block(&mut self, containing_block: Dimensions, containing_block_height: Option<f32>);
+
+    /// Lay out an anonymous block wrapping inline content.
+    ///
+    /// The block takes the whole width of the containing block and establishes an inline
+    /// formatting context: content of its inline descendants is broken into line boxes stacked
+    /// on top of each other, and the block is as tall as all of its lines.
+    ///
+    /// http://www.w3.org/TR/CSS2/visuren.html#inline-formatting
+    fn layout_anonymous_block(&mut self, containing_block: Dimensions);
 
     /// Calculate the width of a block-level non-replaced element in normal flow.
     ///
@@ -147,24 +175,29 @@ pub trait Layoutable {
 
     /// Lay out the block's children within its content area.
     // Sets `self.dimensions.height` to the total content height.
-    fn layout_block_children(&mut self);
+    fn layout_block_children(&mut self, height: Option<f32>);
 
     /// Height of a block-level non-replaced element in normal flow with overflow visible.
-    fn calculate_block_height(&mut self);
+    ///
+    /// `height` is the used value of the `height` property, `None` if it is `auto`.
+    fn calculate_block_height(&mut self, height: Option<f32>);
 
     /// Where a new inline child should go.
     fn get_inline_container(&mut self) -> NodeMut<'_, LayoutBox>;
 }
 
 impl Layoutable for NodeMut<'_, LayoutBox> {
-    fn layout(&mut self, containing_block: Dimensions) {
+    fn layout(&mut self, containing_block: Dimensions, containing_block_height: Option<f32>) {
         // TODO: Support other display types
-        if let BlockNode(_) = &self.value().box_type {
-            self.layout_block(containing_block)
+        match &self.value().box_type {
+            BlockNode(_) => self.layout_block(containing_block, containing_block_height),
+            AnonymousBlock => self.layout_anonymous_block(containing_block),
+            // Inline boxes are laid out as part of the anonymous block containing them.
+            InlineNode(_) => {}
         }
     }
 
-    fn layout_block(&mut self, containing_block: Dimensions) {
+    fn layout_block(&mut self, containing_block: Dimensions, containing_block_height: Option<f32>) {
         // Child width can depend on parent width, so we need to calculate this box's width before
         // laying out its children.
         self.calculate_block_width(containing_block);
@@ -172,12 +205,54 @@ impl Layoutable for NodeMut<'_, LayoutBox> {
         // Determine where the box is located within its container.
         self.calculate_block_position(containing_block);
 
+        // Explicit height is known up front, children with percent heights need it.
+        let height = self
+            .value()
+            .get_style_node()
+            .unwrap()
+            .value::<Height>()
+            .and_then(|height| height.to_px(containing_block_height));
+
         // Recursively lay out the children of this box.
-        self.layout_block_children();
+        self.layout_block_children(height);
 
         // Parent height can depend on child height, so `calculate_height` must be called after the
         // children are laid out.
-        self.calculate_block_height();
+        self.calculate_block_height(height);
+    }
+
+    fn layout_anonymous_block(&mut self, containing_block: Dimensions) {
+        let d = &mut self.value().dimensions;
+        d.content.x = containing_block.content.x;
+        d.content.y = containing_block.content.y + containing_block.content.height;
+        d.content.width = containing_block.content.width;
+        let content = d.content;
+
+        // Every line starts with a zero width inline box with font of the block container. Content
+        // of the first line of the container inherits from its `::first-line` instead. Anonymous
+        // blocks of collapsed whitespace before have no lines.
+        let id = self.id();
+        let starts_container = self.tree().get(id).unwrap().prev_siblings().all(|sibling| {
+            sibling.value().box_type == AnonymousBlock
+                && sibling.value().dimensions.content.height == 0.0
+        });
+        let (strut, first_line) = match self
+            .parent()
+            .map(|mut parent| parent.value().box_type.clone())
+        {
+            Some(BlockNode(style)) => (
+                TextStyle::new(&style),
+                style
+                    .first_line
+                    .as_ref()
+                    .filter(|_| starts_container)
+                    .map(TextStyle::from_props),
+            ),
+            _ => (TextStyle::default(), None),
+        };
+
+        let height = layout_inline_content(self.tree(), id, content, &strut, first_line.as_ref());
+        self.value().dimensions.content.height = height;
     }
 
     fn calculate_block_width(&mut self, containing_block: Dimensions) {
@@ -190,107 +265,36 @@ impl Layoutable for NodeMut<'_, LayoutBox> {
         // margin, border, and padding have initial value 0.
         let zero = Length::Absolute(Absolute::Px(0.0));
 
-        let mut margin_left = style.lookup::<MarginLeft, Margin>(
-            |m| {
-                MarginLeft::MarginWidth(match m {
-                    Margin::Inherit => todo!(),
-                    Margin::MarginV0(margin_widths) => match margin_widths.len() {
-                        1 => *margin_widths.first().unwrap(),
-                        2 => *margin_widths.get(1).unwrap(),
-                        3 => *margin_widths.get(1).unwrap(),
-                        4 => *margin_widths.get(3).unwrap(),
-                        _ => unreachable!(),
-                    },
-                })
-            },
-            MarginLeft::MarginWidth(MarginWidthV::Length(zero)),
-        );
-        let mut margin_right = style.lookup::<MarginRight, Margin>(
-            |m| {
-                MarginRight::MarginWidth(match m {
-                    Margin::Inherit => todo!(),
-                    Margin::MarginV0(margin_widths) => match margin_widths.len() {
-                        1 => *margin_widths.first().unwrap(),
-                        2 => *margin_widths.get(1).unwrap(),
-                        3 => *margin_widths.get(1).unwrap(),
-                        4 => *margin_widths.get(1).unwrap(),
-                        _ => unreachable!(),
-                    },
-                })
-            },
-            MarginRight::MarginWidth(MarginWidthV::Length(zero)),
-        );
-
-        let border_left = style.lookup::<BorderLeftWidth, BorderWidth>(
-            |bw| {
-                BorderLeftWidth::BorderWidth(match bw {
-                    BorderWidth::Inherit => todo!(),
-                    BorderWidth::BorderWidthV0(border_widths) => match border_widths.len() {
-                        1 => *border_widths.first().unwrap(),
-                        2 => *border_widths.get(1).unwrap(),
-                        3 => *border_widths.get(1).unwrap(),
-                        4 => *border_widths.get(3).unwrap(),
-                        _ => unreachable!(),
-                    },
-                })
-            },
-            BorderLeftWidth::BorderWidth(BorderWidthV::Length(zero)),
-        );
-        let border_right = style.lookup::<BorderRightWidth, BorderWidth>(
-            |bw| {
-                BorderRightWidth::BorderWidth(match bw {
-                    BorderWidth::Inherit => todo!(),
-                    BorderWidth::BorderWidthV0(border_widths) => match border_widths.len() {
-                        1 => *border_widths.first().unwrap(),
-                        2 => *border_widths.get(1).unwrap(),
-                        3 => *border_widths.get(1).unwrap(),
-                        4 => *border_widths.get(1).unwrap(),
-                        _ => unreachable!(),
-                    },
-                })
-            },
-            BorderRightWidth::BorderWidth(BorderWidthV::Length(zero)),
-        );
-
-        let padding_left = style.lookup::<PaddingLeft, Padding>(
-            |p| {
-                PaddingLeft::PaddingWidth(match p {
-                    Padding::Inherit => todo!(),
-                    Padding::PaddingV0(padding_widths) => match padding_widths.len() {
-                        1 => *padding_widths.first().unwrap(),
-                        2 => *padding_widths.get(1).unwrap(),
-                        3 => *padding_widths.get(1).unwrap(),
-                        4 => *padding_widths.get(3).unwrap(),
-                        _ => unreachable!(),
-                    },
-                })
-            },
-            PaddingLeft::PaddingWidth(PaddingWidthV::Length(zero)),
-        );
-        let padding_right = style.lookup::<PaddingRight, Padding>(
-            |p| {
-                PaddingRight::PaddingWidth(match p {
-                    Padding::Inherit => todo!(),
-                    Padding::PaddingV0(padding_widths) => match padding_widths.len() {
-                        1 => *padding_widths.first().unwrap(),
-                        2 => *padding_widths.get(1).unwrap(),
-                        3 => *padding_widths.get(1).unwrap(),
-                        4 => *padding_widths.get(1).unwrap(),
-                        _ => unreachable!(),
-                    },
-                })
-            },
-            PaddingRight::PaddingWidth(PaddingWidthV::Length(zero)),
-        );
-
+        let mut margin_left = style
+            .value::<MarginLeft>()
+            .unwrap_or(MarginLeft::MarginWidth(MarginWidthV::Length(zero)));
+        let mut margin_right = style
+            .value::<MarginRight>()
+            .unwrap_or(MarginRight::MarginWidth(MarginWidthV::Length(zero)));
+
+        let border_left = style
+            .value::<BorderLeftWidth>()
+            .unwrap_or(BorderLeftWidth::BorderWidth(BorderWidthV::Length(zero)));
+        let border_right = style
+            .value::<BorderRightWidth>()
+            .unwrap_or(BorderRightWidth::BorderWidth(BorderWidthV::Length(zero)));
+
+        let padding_left = style
+            .value::<PaddingLeft>()
+            .unwrap_or(PaddingLeft::PaddingWidth(PaddingWidthV::Length(zero)));
+        let padding_right = style
+            .value::<PaddingRight>()
+            .unwrap_or(PaddingRight::PaddingWidth(PaddingWidthV::Length(zero)));
+
+        let cb_width = containing_block.content.width;
         let total: f32 = [
-            margin_left.to_px(),
-            margin_right.to_px(),
+            margin_left.to_px(cb_width),
+            margin_right.to_px(cb_width),
             border_left.to_px(),
             border_right.to_px(),
-            padding_left.to_px(),
-            padding_right.to_px(),
-            width.to_px(),
+            padding_left.to_px(cb_width),
+            padding_right.to_px(cb_width),
+            width.to_px(cb_width),
         ]
         .iter()
         .sum();
@@ -322,7 +326,7 @@ impl Layoutable for NodeMut<'_, LayoutBox> {
             // If the values are overconstrained, calculate margin_right.
             (false, false, false) => {
                 margin_right = MarginRight::MarginWidth(MarginWidthV::Length(Length::Absolute(
-                    Absolute::Px(margin_right.to_px() + underflow),
+                    Absolute::Px(margin_right.to_px(cb_width) + underflow),
                 )));
             }
 
@@ -358,7 +362,7 @@ impl Layoutable for NodeMut<'_, LayoutBox> {
                     // Width can't be negative. Adjust the right margin instead.
                     width = Width::Length(Length::Absolute(Absolute::Px(0.0)));
                     margin_right = MarginRight::MarginWidth(MarginWidthV::Length(
-                        Length::Absolute(Absolute::Px(margin_right.to_px() + underflow)),
+                        Length::Absolute(Absolute::Px(margin_right.to_px(cb_width) + underflow)),
                     ));
                 }
             }
@@ -375,16 +379,16 @@ impl Layoutable for NodeMut<'_, LayoutBox> {
         }
 
         let d = &mut self.value().dimensions;
-        d.content.width = width.to_px();
+        d.content.width = width.to_px(cb_width);
 
-        d.padding.left = padding_left.to_px();
-        d.padding.right = padding_right.to_px();
+        d.padding.left = padding_left.to_px(cb_width);
+        d.padding.right = padding_right.to_px(cb_width);
 
         d.border.left = border_left.to_px();
         d.border.right = border_right.to_px();
 
-        d.margin.left = margin_left.to_px();
-        d.margin.right = margin_right.to_px();
+        d.margin.left = margin_left.to_px(cb_width);
+        d.margin.right = margin_right.to_px(cb_width);
     }
 
     fn calculate_block_position(&mut self, containing_block: Dimensions) {
@@ -394,112 +398,36 @@ impl Layoutable for NodeMut<'_, LayoutBox> {
 
         // margin, border, and padding have initial value 0.
         let zero = Length::Absolute(Absolute::Px(0.0));
+        // Percentages of vertical margins and paddings refer to the width too.
+        let cb_width = containing_block.content.width;
 
         // If margin-top or margin-bottom is `auto`, the used value is zero.
         d.margin.top = style
-            .lookup::<MarginTop, Margin>(
-                |m| {
-                    MarginTop::MarginWidth(match m {
-                        Margin::Inherit => todo!(),
-                        Margin::MarginV0(margin_widths) => match margin_widths.len() {
-                            1 => *margin_widths.first().unwrap(),
-                            2 => *margin_widths.first().unwrap(),
-                            3 => *margin_widths.first().unwrap(),
-                            4 => *margin_widths.first().unwrap(),
-                            _ => todo!(),
-                        },
-                    })
-                },
-                MarginTop::MarginWidth(MarginWidthV::Length(zero)),
-            )
-            .to_px();
+            .value::<MarginTop>()
+            .unwrap_or(MarginTop::MarginWidth(MarginWidthV::Length(zero)))
+            .to_px(cb_width);
         d.margin.bottom = style
-            .lookup::<MarginBottom, Margin>(
-                |m| {
-                    MarginBottom::MarginWidth(match m {
-                        Margin::Inherit => todo!(),
-                        Margin::MarginV0(margin_widths) => match margin_widths.len() {
-                            1 => *margin_widths.first().unwrap(),
-                            2 => *margin_widths.first().unwrap(),
-                            3 => *margin_widths.get(3).unwrap(),
-                            4 => *margin_widths.get(3).unwrap(),
-                            _ => todo!(),
-                        },
-                    })
-                },
-                MarginBottom::MarginWidth(MarginWidthV::Length(zero)),
-            )
-            .to_px();
+            .value::<MarginBottom>()
+            .unwrap_or(MarginBottom::MarginWidth(MarginWidthV::Length(zero)))
+            .to_px(cb_width);
 
         d.border.top = style
-            .lookup::<BorderTopWidth, BorderWidth>(
-                |bw| {
-                    BorderTopWidth::BorderWidth(match bw {
-                        BorderWidth::BorderWidthV0(border_widths) => match border_widths.len() {
-                            1 => *border_widths.first().unwrap(),
-                            2 => *border_widths.first().unwrap(),
-                            3 => *border_widths.first().unwrap(),
-                            4 => *border_widths.first().unwrap(),
-                            _ => todo!(),
-                        },
-                        _ => todo!(),
-                    })
-                },
-                BorderTopWidth::BorderWidth(BorderWidthV::Length(zero)),
-            )
+            .value::<BorderTopWidth>()
+            .unwrap_or(BorderTopWidth::BorderWidth(BorderWidthV::Length(zero)))
             .to_px();
         d.border.bottom = style
-            .lookup::<BorderBottomWidth, BorderWidth>(
-                |bw| {
-                    BorderBottomWidth::BorderWidth(match bw {
-                        BorderWidth::BorderWidthV0(border_widths) => match border_widths.len() {
-                            1 => *border_widths.first().unwrap(),
-                            2 => *border_widths.first().unwrap(),
-                            3 => *border_widths.get(3).unwrap(),
-                            4 => *border_widths.get(3).unwrap(),
-                            _ => todo!(),
-                        },
-                        _ => todo!(),
-                    })
-                },
-                BorderBottomWidth::BorderWidth(BorderWidthV::Length(zero)),
-            )
+            .value::<BorderBottomWidth>()
+            .unwrap_or(BorderBottomWidth::BorderWidth(BorderWidthV::Length(zero)))
             .to_px();
 
         d.padding.top = style
-            .lookup::<PaddingTop, Padding>(
-                |m| {
-                    PaddingTop::PaddingWidth(match m {
-                        Padding::Inherit => todo!(),
-                        Padding::PaddingV0(padding_widths) => match padding_widths.len() {
-                            1 => *padding_widths.first().unwrap(),
-                            2 => *padding_widths.first().unwrap(),
-                            3 => *padding_widths.first().unwrap(),
-                            4 => *padding_widths.first().unwrap(),
-                            _ => todo!(),
-                        },
-                    })
-                },
-                PaddingTop::PaddingWidth(PaddingWidthV::Length(zero)),
-            )
-            .to_px();
+            .value::<PaddingTop>()
+            .unwrap_or(PaddingTop::PaddingWidth(PaddingWidthV::Length(zero)))
+            .to_px(cb_width);
         d.padding.bottom = style
-            .lookup::<PaddingBottom, Padding>(
-                |m| {
-                    PaddingBottom::PaddingWidth(match m {
-                        Padding::Inherit => todo!(),
-                        Padding::PaddingV0(padding_widths) => match padding_widths.len() {
-                            1 => *padding_widths.first().unwrap(),
-                            2 => *padding_widths.first().unwrap(),
-                            3 => *padding_widths.get(3).unwrap(),
-                            4 => *padding_widths.get(3).unwrap(),
-                            _ => todo!(),
-                        },
-                    })
-                },
-                PaddingBottom::PaddingWidth(PaddingWidthV::Length(zero)),
-            )
-            .to_px();
+            .value::<PaddingBottom>()
+            .unwrap_or(PaddingBottom::PaddingWidth(PaddingWidthV::Length(zero)))
+            .to_px(cb_width);
 
         d.content.x = containing_block.content.x + d.margin.left + d.border.left + d.padding.left;
 
@@ -511,10 +439,10 @@ impl Layoutable for NodeMut<'_, LayoutBox> {
             + d.padding.top;
     }
 
-    fn layout_block_children(&mut self) {
+    fn layout_block_children(&mut self, height: Option<f32>) {
         self.for_each_child(|child| {
             let parent_dimensions = child.parent().unwrap().value().dimensions;
-            child.layout(parent_dimensions);
+            child.layout(parent_dimensions, height);
 
             let child_layouted_dimensions = child.value().dimensions;
             // SAFE: We already checked that parent is in there.
@@ -524,13 +452,11 @@ impl Layoutable for NodeMut<'_, LayoutBox> {
         });
     }
 
-    fn calculate_block_height(&mut self) {
+    fn calculate_block_height(&mut self, height: Option<f32>) {
         // If the height is set to an explicit length, use that exact length.
         // Otherwise, just keep the value set by `layout_block_children`.
-        if let Some(Height::Length(Length::Absolute(Absolute::Px(h)))) =
-            self.value().get_style_node().unwrap().value::<Height>()
-        {
-            self.value().dimensions.content.height = h;
+        if let Some(height) = height {
+            self.value().dimensions.content.height = height;
         }
     }
 
@@ -557,7 +483,285 @@ impl Layoutable for NodeMut<'_, LayoutBox> {
     }
 }
 
+/// Word of a text box together with everything needed to place it on a line.
+struct InlineSegment {
+    /// Text box the word belongs to.
+    text_box: NodeId,
+    /// Inline boxes containing the text box, innermost first.
+    ancestors: Vec<NodeId>,
+    word: ShapedWord,
+    metrics: FontMetrics,
+    /// Height of the inline box above and below the baseline, including half-leading.
+    above: f32,
+    below: f32,
+}
+
+impl InlineSegment {
+    /// Whether line can be broken after this segment.
+    fn is_break_opportunity(&self) -> bool {
+        self.word.space_width > 0.0
+    }
+}
+
+/// Height of the inline box above and below the baseline.
+///
+/// Difference between `line-height` and the font height is split in half between top and bottom.
+///
+/// http://www.w3.org/TR/CSS2/visudet.html#leading
+fn inline_box_height(style: &TextStyle, metrics: FontMetrics) -> (f32, f32) {
+    let half_leading = (style.line_height() - (metrics.ascent + metrics.descent)) / 2.0;
+    (
+        metrics.ascent + half_leading,
+        metrics.descent + half_leading,
+    )
+}
+
+/// Break inline content of the `block` into line boxes within `content` area and position
+/// inline boxes on them. Returns total height of the lines.
+///
+/// If the first line of the block container is styled by `::first-line`, its strut has
+/// `first_line` style and text on it is styled with [`StyledNode::first_line`] values. The `block`
+/// gets a fragment covering the content of the line then, to paint the pseudo-element on.
+fn layout_inline_content(
+    tree: &mut LayoutTree,
+    block: NodeId,
+    content: Rect,
+    strut: &TextStyle,
+    first_line: Option<&TextStyle>,
+) -> f32 {
+    let mut segments = inline_segments(tree.get(block).unwrap(), false);
+    if segments.is_empty() {
+        return 0.0;
+    }
+    let mut lines = break_lines(&segments, content.width);
+    let mut first_strut = strut.clone();
+    let mut first_line_box = None;
+
+    if let Some(first_line) = first_line {
+        let first_line_segments = inline_segments(tree.get(block).unwrap(), true);
+        // Words are split at the same spaces in both styles, so segments correspond one to one.
+        if first_line_segments.len() == segments.len() {
+            let end = break_lines(&first_line_segments, content.width)[0].end;
+            segments.splice(..end, first_line_segments.into_iter().take(end));
+            lines = std::iter::once(0..end)
+                .chain(
+                    break_lines(&segments[end..], content.width)
+                        .into_iter()
+                        .map(|range| range.start + end..range.end + end)
+                        .filter(|range| !range.is_empty()),
+                )
+                .collect();
+            first_strut = first_line.clone();
+            first_line_box = Some(block);
+        }
+    }
+
+    let first_strut = inline_box_height(&first_strut, text::font_metrics(&first_strut));
+    let strut = inline_box_height(strut, text::font_metrics(strut));
+
+    let mut fragments: HashMap<NodeId, Vec<(usize, Rect)>> = HashMap::new();
+    let mut text_runs: HashMap<NodeId, Vec<(usize, TextRun)>> = HashMap::new();
+    let mut line_top = content.y;
+    for (line, range) in lines.into_iter().enumerate() {
+        let segments = &segments[range];
+        let (strut_above, strut_below) = if line == 0 { first_strut } else { strut };
+        let above = segments
+            .iter()
+            .map(|segment| segment.above)
+            .fold(strut_above, f32::max);
+        let below = segments
+            .iter()
+            .map(|segment| segment.below)
+            .fold(strut_below, f32::max);
+        let baseline = line_top + above;
+
+        let mut x = content.x;
+        for (i, segment) in segments.iter().enumerate() {
+            // Trailing spaces of the last word hang outside of the line.
+            let width = if i + 1 == segments.len() {
+                segment.word.width - segment.word.space_width
+            } else {
+                segment.word.width
+            };
+            let rect = Rect {
+                x,
+                y: baseline - segment.metrics.ascent,
+                width,
+                height: segment.metrics.ascent + segment.metrics.descent,
+            };
+
+            let line_box = first_line_box.as_ref().filter(|_| line == 0);
+            for &id in std::iter::once(&segment.text_box)
+                .chain(&segment.ancestors)
+                .chain(line_box)
+            {
+                let box_fragments = fragments.entry(id).or_default();
+                match box_fragments.last_mut() {
+                    Some((fragment_line, fragment)) if *fragment_line == line => {
+                        *fragment = fragment.union(rect);
+                    }
+                    _ => box_fragments.push((line, rect)),
+                }
+            }
+
+            let runs = text_runs.entry(segment.text_box).or_default();
+            match runs.last_mut() {
+                Some((run_line, run)) if *run_line == line => {
+                    let offset = x - run.x;
+                    run.glyphs
+                        .extend(segment.word.glyphs.iter().cloned().map(|mut glyph| {
+                            glyph.x += offset;
+                            glyph
+                        }));
+                }
+                _ => runs.push((
+                    line,
+                    TextRun {
+                        x,
+                        baseline,
+                        glyphs: segment.word.glyphs.clone(),
+                    },
+                )),
+            }
+
+            x += segment.word.width;
+        }
+
+        line_top = baseline + below;
+    }
+
+    // The block keeps its dimensions, unlike the inline boxes.
+    if let Some(line) = fragments.remove(&block) {
+        tree.get_mut(block).unwrap().value().fragments =
+            line.into_iter().map(|(_, rect)| rect).collect();
+    }
+    for (id, box_fragments) in fragments {
+        let mut node = tree.get_mut(id).unwrap();
+        let layout_box = node.value();
+        layout_box.fragments = box_fragments.into_iter().map(|(_, rect)| rect).collect();
+        layout_box.dimensions.content = layout_box
+            .fragments
+            .iter()
+            .copied()
+            .reduce(Rect::union)
+            .unwrap_or_default();
+    }
+    for (id, runs) in text_runs {
+        tree.get_mut(id).unwrap().value().text_runs =
+            runs.into_iter().map(|(_, run)| run).collect();
+    }
+
+    line_top - content.y
+}
+
+/// Split segments into lines within `width` greedily, line can only be broken after a space.
+fn break_lines(segments: &[InlineSegment], width: f32) -> Vec<std::ops::Range<usize>> {
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    let mut line_width = 0.0;
+    let mut chunk_start = 0;
+    let mut chunk_width = 0.0;
+    for (i, segment) in segments.iter().enumerate() {
+        chunk_width += segment.word.width;
+        let last = i + 1 == segments.len();
+        if !(segment.is_break_opportunity() || last) {
+            continue;
+        }
+
+        // Spaces at the end of a line are not visible, so they may overflow it.
+        let visible_width = line_width + chunk_width - segment.word.space_width;
+        if visible_width > width && chunk_start > line_start {
+            lines.push(line_start..chunk_start);
+            line_start = chunk_start;
+            line_width = 0.0;
+        }
+        line_width += chunk_width;
+        chunk_start = i + 1;
+        chunk_width = 0.0;
+    }
+    lines.push(line_start..segments.len());
+    lines
+}
+
+/// Shape text of inline descendants of the box in document order and split it into words. Text
+/// takes its values on the `first_line` of the block container if requested, see
+/// [`StyledNode::first_line`].
+fn inline_segments(layout_box: NodeRef<'_, LayoutBox>, first_line: bool) -> Vec<InlineSegment> {
+    let spans = layout_box
+        .descendants()
+        .filter_map(|child| match &child.value().box_type {
+            InlineNode(
+                style @ StyledNode {
+                    node: Node::Text(text),
+                    ..
+                },
+            ) => {
+                let ancestors = child
+                    .ancestors()
+                    .take_while(|ancestor| ancestor.id() != layout_box.id())
+                    .map(|ancestor| ancestor.id())
+                    .collect::<Vec<_>>();
+                let style = match &style.first_line {
+                    Some(props) if first_line => TextStyle::from_props(props),
+                    _ => TextStyle::new(style),
+                };
+                Some((text.to_string(), (child.id(), ancestors, style)))
+            }
+            _ => None,
+        });
+
+    let mut segments = Vec::new();
+    for (text, (text_box, ancestors, style)) in text::collapse_whitespace(spans) {
+        let (words, metrics) = text::shape_words(&text, &style);
+        let (above, below) = inline_box_height(&style, metrics);
+        segments.extend(words.into_iter().map(|word| InlineSegment {
+            text_box,
+            ancestors: ancestors.clone(),
+            word,
+            metrics,
+            above,
+            below,
+        }));
+    }
+    segments
+}
+
+/// Find the DOM node whose box is painted topmost at point `(x, y)` of the document.
+///
+/// Inline boxes are hit only within their fragments, block boxes within their border box. Boxes
+/// later in the tree are painted on top of earlier ones.
+pub fn hit_test(root: NodeRef<'_, LayoutBox>, x: f32, y: f32) -> Option<NodeId> {
+    root.descendants()
+        .filter_map(|layout_box| {
+            let layout_box = layout_box.value();
+            let hit = match &layout_box.box_type {
+                BlockNode(_) => layout_box.dimensions.border_box().contains(x, y),
+                InlineNode(_) => layout_box.fragments.iter().any(|rect| rect.contains(x, y)),
+                AnonymousBlock => false,
+            };
+            hit.then(|| layout_box.get_style_node().unwrap().id)
+        })
+        .last()
+}
+
 impl Rect {
+    /// Returns true if point `(x, y)` lies within the rectangle.
+    pub fn contains(self, x: f32, y: f32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// The smallest rectangle containing both rectangles.
+    pub fn union(self, other: Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+
     pub fn expanded_by(self, edge: EdgeSizes) -> Rect {
         Rect {
             x: self.x - edge.left,
@@ -0,0 +1,17 @@
+//! WBP (Web Browser Project) is a lightweight, from-scratch web browser engine.
+//!
+//! Most embedders only need [`Engine`], which drives the whole pipeline from HTML source to
+//! pixels. Separate stages are available in corresponding modules.
+
+pub mod css;
+pub mod engine;
+pub mod headless;
+pub mod html;
+pub mod layout;
+pub mod loader;
+pub mod painting;
+pub mod selector;
+pub mod style;
+pub mod text;
+
+pub use engine::{Document, Engine, Viewport};
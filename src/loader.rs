@@ -0,0 +1,89 @@
+//! Fetching of resources referenced by documents, e.g. external style sheets.
+//!
+//! Engine does not do any I/O by itself, instead it asks a [`ResourceLoader`] for contents of a
+//! URL. [`FileLoader`] serves `file:` URLs from the local file system.
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::{fs, io};
+
+use url::Url;
+
+/// Source of resources referenced by documents.
+pub trait ResourceLoader: Debug {
+    /// Fetch text contents of the resource at `url`.
+    fn load(&self, url: &Url) -> Result<String, LoadError>;
+}
+
+/// Error returned when a resource cannot be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    /// Loader does not know how to fetch URLs with this scheme.
+    UnsupportedScheme(String),
+    /// URL does not point to a local file.
+    NotAFilePath(Url),
+    /// Reading of the resource failed.
+    Io(io::Error),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedScheme(scheme) => write!(f, "unsupported URL scheme `{scheme}`"),
+            Self::NotAFilePath(url) => write!(f, "{url} is not a path to a local file"),
+            Self::Io(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Loader of `file:` URLs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileLoader;
+
+impl ResourceLoader for FileLoader {
+    fn load(&self, url: &Url) -> Result<String, LoadError> {
+        if url.scheme() != "file" {
+            return Err(LoadError::UnsupportedScheme(url.scheme().to_owned()));
+        }
+        let path = url
+            .to_file_path()
+            .map_err(|()| LoadError::NotAFilePath(url.clone()))?;
+        Ok(fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_loader_reads_file_urls_only() {
+        let path = std::env::temp_dir().join(format!("wbp-loader-{}.css", std::process::id()));
+        fs::write(&path, "p { color: red }").unwrap();
+        let url = Url::from_file_path(&path).unwrap();
+
+        assert_eq!(FileLoader.load(&url).unwrap(), "p { color: red }");
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(FileLoader.load(&url), Err(LoadError::Io(_))));
+
+        let url = Url::parse("https://example.com/style.css").unwrap();
+        assert!(matches!(
+            FileLoader.load(&url),
+            Err(LoadError::UnsupportedScheme(scheme)) if scheme == "https"
+        ));
+    }
+}
@@ -1,31 +1,30 @@
-use crate::painting::PixelBuffer;
+use wbp::css::{self, StyleSheet};
+use wbp::{headless, Engine, Viewport};
 
 use std::fs::{self, File};
 use std::io::Read;
 use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::{mpsc, Arc, Mutex};
 use std::time::UNIX_EPOCH;
 
+use clap::Parser;
 use tiny_skia::Pixmap;
+use url::Url;
 
 use log::*;
 
-use winit::event::{Event, KeyEvent, WindowEvent};
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, Event, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::keyboard::{Key, NamedKey};
 use winit::window::Window;
 
-pub mod css;
-pub mod html;
-pub mod layout;
-pub mod painting;
-pub mod selector;
-pub mod style;
+pub mod cli;
 pub mod winit_app;
 
-const HTML_FILE_PATH: &str = "test.html";
-
-pub fn read_source(filename: &str) -> String {
+pub fn read_source(filename: impl AsRef<Path>) -> String {
     let mut s = String::new();
     File::open(filename)
         .unwrap()
@@ -34,7 +33,13 @@ pub fn read_source(filename: &str) -> String {
     s
 }
 
-pub fn file_modified_time_in_seconds(path: &str) -> u64 {
+/// `file:` URL of the document at `path`, used to resolve relative URLs of its resources.
+pub fn document_url(path: impl AsRef<Path>) -> Option<Url> {
+    let path = fs::canonicalize(path).ok()?;
+    Url::from_file_path(path).ok()
+}
+
+pub fn file_modified_time_in_seconds(path: impl AsRef<Path>) -> u64 {
     fs::metadata(path)
         .unwrap()
         .modified()
@@ -46,14 +51,116 @@ pub fn file_modified_time_in_seconds(path: &str) -> u64 {
 
 type Surface = softbuffer::Surface<Arc<Window>, Arc<Window>>;
 
+/// Distance scrolled by an arrow key or a single notch of the mouse wheel, in px.
+const LINE_HEIGHT: f32 = 40.0;
+
+/// Change of the scroll position requested by the user.
+#[derive(Clone, Copy, Debug)]
+enum Scroll {
+    /// Scroll by a number of px, positive values scroll down.
+    By(f32),
+    /// Scroll by a number of viewport heights, keeping one line of the previous page visible.
+    Pages(f32),
+    Top,
+    Bottom,
+}
+
+impl Scroll {
+    /// Scroll requested by the pressed key, if any.
+    fn from_key(key: &Key) -> Option<Self> {
+        match key {
+            Key::Named(NamedKey::ArrowUp) => Some(Self::By(-LINE_HEIGHT)),
+            Key::Named(NamedKey::ArrowDown) => Some(Self::By(LINE_HEIGHT)),
+            Key::Named(NamedKey::PageUp) => Some(Self::Pages(-1.0)),
+            Key::Named(NamedKey::PageDown) => Some(Self::Pages(1.0)),
+            Key::Named(NamedKey::Home) => Some(Self::Top),
+            Key::Named(NamedKey::End) => Some(Self::Bottom),
+            _ => None,
+        }
+    }
+
+    /// Scroll requested by the mouse wheel or touchpad.
+    fn from_wheel(delta: MouseScrollDelta) -> Self {
+        // Wheel deltas are positive when content should move down, i.e. when scrolling up.
+        match delta {
+            MouseScrollDelta::LineDelta(_, lines) => Self::By(-lines * LINE_HEIGHT),
+            MouseScrollDelta::PixelDelta(position) => Self::By(-position.y as f32),
+        }
+    }
+
+    fn apply(self, engine: &mut Engine) {
+        match self {
+            Scroll::By(dy) => engine.scroll_by(dy),
+            Scroll::Pages(pages) => {
+                let page = (engine.viewport().height - LINE_HEIGHT).max(LINE_HEIGHT);
+                engine.scroll_by(pages * page);
+            }
+            Scroll::Top => engine.scroll_to(0.0),
+            Scroll::Bottom => engine.scroll_to(f32::INFINITY),
+        }
+    }
+}
+
+/// Input from the user, applied to the engine by the render thread.
+#[derive(Clone, Copy, Debug)]
+enum Input {
+    Scroll(Scroll),
+    /// Pointer moved to a position in the window, in physical px.
+    PointerMoved(f32, f32),
+    PointerLeft,
+    PointerPressed,
+    PointerReleased,
+    /// Move focus to the next focusable element, or to the previous one if `true`.
+    FocusNext(bool),
+    /// Activate the focused element.
+    Activate,
+}
+
+impl Input {
+    /// Input requested by the pressed key, if any.
+    fn from_key(key: &Key, shift: bool) -> Option<Self> {
+        match key {
+            Key::Named(NamedKey::Tab) => Some(Self::FocusNext(shift)),
+            Key::Named(NamedKey::Enter | NamedKey::Space) => Some(Self::Activate),
+            key => Scroll::from_key(key).map(Self::Scroll),
+        }
+    }
+
+    fn apply(self, engine: &mut Engine) {
+        match self {
+            Input::Scroll(scroll) => scroll.apply(engine),
+            Input::PointerMoved(x, y) => engine.pointer_moved(x, y),
+            Input::PointerLeft => engine.pointer_left(),
+            Input::PointerPressed => engine.pointer_pressed(),
+            Input::PointerReleased => engine.pointer_released(),
+            Input::FocusNext(backwards) => engine.focus_next(backwards),
+            Input::Activate => engine.activate_focused(),
+        }
+    }
+}
+
+/// Request to render a frame, sent from the event loop to the render thread.
+struct RenderRequest {
+    surface: Arc<Mutex<Surface>>,
+    /// Input received since the previous frame, in order.
+    inputs: Vec<Input>,
+}
+
 fn render_thread(
     window: Arc<Window>,
-    do_render: mpsc::Receiver<Arc<Mutex<Surface>>>,
+    document: PathBuf,
+    user_stylesheet: StyleSheet,
+    do_render: mpsc::Receiver<RenderRequest>,
     done: mpsc::Sender<()>,
 ) {
+    let mut engine = Engine::default();
+    engine.set_user_stylesheet(user_stylesheet);
+    // Modification time of the document when it was last loaded into the engine.
+    let mut loaded_at = None;
+
     loop {
         debug!("waiting for render...");
-        let Ok(surface) = do_render.recv() else {
+        let Ok(RenderRequest { surface, inputs }) = do_render.recv() else {
             info!("main thread destroyed");
             break;
         };
@@ -73,29 +180,19 @@ fn render_thread(
             if let Some(mut pixmap) = Pixmap::new(width.get(), height.get()) {
                 pixmap.fill(tiny_skia::Color::WHITE);
 
-                let html = read_source(HTML_FILE_PATH);
-                let document = crate::html::Html::parse_fragment(&html);
-                // debug!("Document tree: {:#?}", document.tree);
-                // debug!("{}", document.tree);
-                let stylesheet = css::from_style(&document);
-                let style_tree = style::style_tree(&document.tree, &stylesheet);
-
-                let screen_dimensions = layout::Dimensions {
-                    content: layout::Rect {
-                        width: width.get() as f32,
-                        // height: height.get() as f32,
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                };
-
-                // Update internal state and request a redraw
-                let layout_tree = layout::layout_tree(
-                    style_tree.root().first_child().unwrap(), // Omit Document node as it treated as inline
-                    screen_dimensions,
-                );
-
-                pixmap.paint(layout_tree.root());
+                engine.set_viewport(Viewport {
+                    width: width.get() as f32,
+                    height: height.get() as f32,
+                });
+                let modified = file_modified_time_in_seconds(&document);
+                if loaded_at != Some(modified) {
+                    loaded_at = Some(modified);
+                    engine.load_html_with_url(&read_source(&document), document_url(&document));
+                }
+                for input in inputs {
+                    input.apply(&mut engine);
+                }
+                engine.paint_into(&mut pixmap);
 
                 // Copy tiny_skia pixmap to the window buffer
                 let bytes = pixmap.data();
@@ -132,29 +229,44 @@ fn render_thread(
     }
 }
 
-pub fn entry(event_loop: EventLoop<()>) {
-    let mut html_last_changed = file_modified_time_in_seconds(HTML_FILE_PATH);
+pub fn entry(
+    event_loop: EventLoop<()>,
+    document: PathBuf,
+    user_stylesheet: StyleSheet,
+    width: u32,
+    height: u32,
+) {
+    let mut html_last_changed = file_modified_time_in_seconds(&document);
+    // Input is applied by the render thread, since it owns the engine.
+    let mut pending_inputs = Vec::new();
+    let mut shift = false;
 
     let app = winit_app::WinitAppBuilder::with_init(
-        |elwt| {
-            let attributes = Window::default_attributes();
-            let window = Arc::new(elwt.create_window(attributes).unwrap());
-
-            let context = softbuffer::Context::new(window.clone()).unwrap();
-
-            // Spawn a thread to handle rendering for this specific surface. The channels will
-            // be closed and the thread will be stopped whenever this surface (the returned
-            // context below) is dropped, so that it can all be recreated again (on Android)
-            // when a new surface is created.
-            let (start_render, do_render) = mpsc::channel();
-            let (render_done, finish_render) = mpsc::channel();
-            debug!("starting thread...");
-            std::thread::spawn({
-                let window = window.clone();
-                move || render_thread(window, do_render, render_done)
-            });
-
-            (window, context, start_render, finish_render)
+        {
+            let document = document.clone();
+            move |elwt| {
+                let attributes =
+                    Window::default_attributes().with_inner_size(LogicalSize::new(width, height));
+                let window = Arc::new(elwt.create_window(attributes).unwrap());
+
+                let context = softbuffer::Context::new(window.clone()).unwrap();
+
+                // Spawn a thread to handle rendering for this specific surface. The channels will
+                // be closed and the thread will be stopped whenever this surface (the returned
+                // context below) is dropped, so that it can all be recreated again (on Android)
+                // when a new surface is created.
+                let (start_render, do_render) = mpsc::channel();
+                let (render_done, finish_render) = mpsc::channel();
+                debug!("starting thread...");
+                std::thread::spawn({
+                    let window = window.clone();
+                    let document = document.clone();
+                    let user_stylesheet = user_stylesheet.clone();
+                    move || render_thread(window, document, user_stylesheet, do_render, render_done)
+                });
+
+                (window, context, start_render, finish_render)
+            }
         },
         |_elwt, (window, context, _start_render, _finish_render)| {
             debug!("making surface...");
@@ -168,7 +280,7 @@ pub fn entry(event_loop: EventLoop<()>) {
         elwt.set_control_flow(ControlFlow::Wait);
 
         // Reload files if they changed on drive.
-        let html_last_changed_now = file_modified_time_in_seconds(HTML_FILE_PATH);
+        let html_last_changed_now = file_modified_time_in_seconds(&document);
 
         if html_last_changed != html_last_changed_now {
             html_last_changed = html_last_changed_now;
@@ -186,9 +298,62 @@ pub fn entry(event_loop: EventLoop<()>) {
                     return;
                 };
                 // Start the render and then finish it.
-                start_render.send(surface.clone()).unwrap();
+                start_render
+                    .send(RenderRequest {
+                        surface: surface.clone(),
+                        inputs: std::mem::take(&mut pending_inputs),
+                    })
+                    .unwrap();
                 finish_render.recv().unwrap();
             }
+            Event::WindowEvent {
+                event: WindowEvent::MouseWheel { delta, .. },
+                window_id,
+            } if window_id == window.id() => {
+                pending_inputs.push(Input::Scroll(Scroll::from_wheel(delta)));
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                window_id,
+            } if window_id == window.id() => {
+                let moved = Input::PointerMoved(position.x as f32, position.y as f32);
+                // Every move can restyle the page, only the latest position matters until the
+                // pointer is pressed or released there.
+                match pending_inputs.last_mut() {
+                    Some(last @ Input::PointerMoved(..)) => *last = moved,
+                    _ => pending_inputs.push(moved),
+                }
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorLeft { .. },
+                window_id,
+            } if window_id == window.id() => {
+                pending_inputs.push(Input::PointerLeft);
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state,
+                        button: MouseButton::Left,
+                        ..
+                    },
+                window_id,
+            } if window_id == window.id() => {
+                pending_inputs.push(match state {
+                    ElementState::Pressed => Input::PointerPressed,
+                    ElementState::Released => Input::PointerReleased,
+                });
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(modifiers),
+                window_id,
+            } if window_id == window.id() => {
+                shift = modifiers.state().shift_key();
+            }
             Event::WindowEvent {
                 event:
                     WindowEvent::CloseRequested
@@ -204,6 +369,24 @@ pub fn entry(event_loop: EventLoop<()>) {
             } if window_id == window.id() => {
                 elwt.exit();
             }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        event:
+                            KeyEvent {
+                                logical_key,
+                                state: ElementState::Pressed,
+                                ..
+                            },
+                        ..
+                    },
+                window_id,
+            } if window_id == window.id() => {
+                if let Some(input) = Input::from_key(&logical_key, shift) {
+                    pending_inputs.push(input);
+                    window.request_redraw();
+                }
+            }
             _ => {}
         }
     });
@@ -211,8 +394,76 @@ pub fn entry(event_loop: EventLoop<()>) {
     winit_app::run_app(event_loop, app);
 }
 
-fn main() {
+fn main() -> ExitCode {
     use winit::event_loop::EventLoop;
-    env_logger::init();
-    entry(EventLoop::new().unwrap());
+
+    let cli = cli::Cli::parse();
+
+    let mut logger = env_logger::Builder::from_default_env();
+    if let Some(level) = cli.log_level {
+        logger.filter_level(level);
+    }
+    logger.init();
+
+    let user_stylesheet = match &cli.user_stylesheet {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(source) => {
+                let (stylesheet, diagnostics) = css::parse_stylesheet(&source);
+                for diagnostic in diagnostics {
+                    warn!("{}:{diagnostic}", path.display());
+                }
+                stylesheet
+            }
+            Err(err) => {
+                error!("Cannot read {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => StyleSheet::new(),
+    };
+
+    match cli.command {
+        Some(cli::Command::Render {
+            input,
+            width,
+            height,
+            out,
+        }) => {
+            let html = match fs::read_to_string(&input) {
+                Ok(html) => html,
+                Err(err) => {
+                    error!("Cannot read {}: {err}", input.display());
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            let Some(pixmap) =
+                headless::render(&html, document_url(&input), user_stylesheet, width, height)
+            else {
+                error!("Cannot render page into an empty image");
+                return ExitCode::FAILURE;
+            };
+
+            if let Err(err) = pixmap.save_png(&out) {
+                error!("Cannot save {}: {err}", out.display());
+                return ExitCode::FAILURE;
+            }
+        }
+        None => {
+            if let Err(err) = fs::metadata(&cli.document) {
+                error!("Cannot open {}: {err}", cli.document.display());
+                return ExitCode::FAILURE;
+            }
+
+            entry(
+                EventLoop::new().unwrap(),
+                cli.document,
+                user_stylesheet,
+                cli.width,
+                cli.height,
+            )
+        }
+    }
+
+    ExitCode::SUCCESS
 }
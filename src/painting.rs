@@ -1,10 +1,14 @@
 // This type replaces Canvas from the original article.
 use crate::{
     css::{
-        props::{Background, BorderColor, PropUnion, Property},
+        props::{
+            BackgroundColor, BorderBottomColor, BorderLeftColor, BorderRightColor, BorderTopColor,
+            PropUnion, Property,
+        },
         values::Color,
     },
     layout::{AnonymousBlock, BlockNode, InlineNode, LayoutBox, Rect},
+    text::{self, TextRun},
 };
 
 use ego_tree::*;
@@ -13,6 +17,7 @@ use tiny_skia::Pixmap;
 #[derive(Debug)]
 pub enum DisplayCommand {
     SolidColor(Color, Rect),
+    Text(TextRun),
 }
 
 pub type DisplayList = Vec<DisplayCommand>;
@@ -26,113 +31,179 @@ pub fn build_display_list(layout_root: NodeRef<'_, LayoutBox>) -> DisplayList {
 fn render_layout_box(list: &mut DisplayList, layout_box: NodeRef<'_, LayoutBox>) {
     render_background(list, layout_box);
     render_borders(list, layout_box);
+    render_text(list, layout_box);
     for child in layout_box.children() {
         render_layout_box(list, child);
     }
 }
 
 fn render_background(list: &mut DisplayList, layout_box: NodeRef<'_, LayoutBox>) {
-    // todo!()
-    if let Some(color) = get_color::<Background>(layout_box) {
-        list.push(DisplayCommand::SolidColor(
-            color,
-            layout_box.value().dimensions.border_box(),
-        ));
+    let color = match layout_box.value().box_type {
+        AnonymousBlock => first_line_background(layout_box),
+        _ => get_color::<BackgroundColor>(layout_box),
+    };
+    if let Some(color) = color {
+        let layout_box = layout_box.value();
+        if layout_box.fragments.is_empty() {
+            list.push(DisplayCommand::SolidColor(
+                color,
+                layout_box.dimensions.border_box(),
+            ));
+        } else {
+            // Inline box is painted line by line.
+            list.extend(
+                layout_box
+                    .fragments
+                    .iter()
+                    .map(|&fragment| DisplayCommand::SolidColor(color, fragment)),
+            );
+        }
     }
 }
 
 fn render_borders(list: &mut DisplayList, layout_box: NodeRef<LayoutBox>) {
-    let color = match get_color::<BorderColor>(layout_box) {
-        Some(color) => color,
-        _ => return,
-    };
-
     let d = &layout_box.value().dimensions;
     let border_box = d.border_box();
 
     // Left border
-    list.push(DisplayCommand::SolidColor(
-        color,
-        Rect {
-            x: border_box.x,
-            y: border_box.y,
-            width: d.border.left,
-            height: border_box.height,
-        },
-    ));
+    if let Some(color) = get_color::<BorderLeftColor>(layout_box) {
+        list.push(DisplayCommand::SolidColor(
+            color,
+            Rect {
+                x: border_box.x,
+                y: border_box.y,
+                width: d.border.left,
+                height: border_box.height,
+            },
+        ));
+    }
 
     // Right border
-    list.push(DisplayCommand::SolidColor(
-        color,
-        Rect {
-            x: border_box.x + border_box.width - d.border.right,
-            y: border_box.y,
-            width: d.border.right,
-            height: border_box.height,
-        },
-    ));
+    if let Some(color) = get_color::<BorderRightColor>(layout_box) {
+        list.push(DisplayCommand::SolidColor(
+            color,
+            Rect {
+                x: border_box.x + border_box.width - d.border.right,
+                y: border_box.y,
+                width: d.border.right,
+                height: border_box.height,
+            },
+        ));
+    }
 
     // Top border
-    list.push(DisplayCommand::SolidColor(
-        color,
-        Rect {
-            x: border_box.x,
-            y: border_box.y,
-            width: border_box.width,
-            height: d.border.top,
-        },
-    ));
+    if let Some(color) = get_color::<BorderTopColor>(layout_box) {
+        list.push(DisplayCommand::SolidColor(
+            color,
+            Rect {
+                x: border_box.x,
+                y: border_box.y,
+                width: border_box.width,
+                height: d.border.top,
+            },
+        ));
+    }
 
     // Bottom border
-    list.push(DisplayCommand::SolidColor(
-        color,
-        Rect {
-            x: border_box.x,
-            y: border_box.y + border_box.height - d.border.bottom,
-            width: border_box.width,
-            height: d.border.bottom,
-        },
-    ));
+    if let Some(color) = get_color::<BorderBottomColor>(layout_box) {
+        list.push(DisplayCommand::SolidColor(
+            color,
+            Rect {
+                x: border_box.x,
+                y: border_box.y + border_box.height - d.border.bottom,
+                width: border_box.width,
+                height: d.border.bottom,
+            },
+        ));
+    }
+}
+
+fn render_text(list: &mut DisplayList, layout_box: NodeRef<'_, LayoutBox>) {
+    list.extend(
+        layout_box
+            .value()
+            .text_runs
+            .iter()
+            .cloned()
+            .map(DisplayCommand::Text),
+    );
 }
 
-/// Return the specified color for CSS property `name`, or None if no color was specified.
-fn get_color<T: Property + Clone + Into<Color>>(layout_box: NodeRef<LayoutBox>) -> Option<Color>
+/// Return the background color of `::first-line` of the block container of an anonymous block
+/// that starts the container.
+///
+/// Backgrounds of `::first-line` of outer blocks are not painted on the first line of a nested
+/// block.
+fn first_line_background(layout_box: NodeRef<LayoutBox>) -> Option<Color> {
+    if layout_box.value().fragments.is_empty() {
+        return None;
+    }
+    match &layout_box.parent()?.value().box_type {
+        BlockNode(style) => style
+            .first_line
+            .as_ref()?
+            .get::<BackgroundColor>()
+            .cloned()
+            .and_then(|v| v.into()),
+        _ => None,
+    }
+}
+
+/// Return the specified color for CSS property `T`, or None if no color was specified or it is
+/// transparent.
+fn get_color<T: Property + Clone + Into<Option<Color>>>(
+    layout_box: NodeRef<LayoutBox>,
+) -> Option<Color>
 where
     for<'a> &'a T: From<&'a PropUnion>,
 {
     match &layout_box.value().box_type {
-        BlockNode(style) | InlineNode(style) => style.value::<T>().map(|v| v.into()),
+        BlockNode(style) | InlineNode(style) => style.value::<T>().and_then(|v| v.into()),
         AnonymousBlock => None,
     }
 }
 
 /// Represents backend for painting.
 pub trait PixelBuffer: Sized {
-    /// Paint one item on pixel buffer.
-    fn paint_item(&mut self, item: &DisplayCommand);
+    /// Paint one item on pixel buffer, moved down by `offset_y`.
+    fn paint_item(&mut self, item: &DisplayCommand, offset_y: f32);
+
+    /// Paint every item of already built display list, moved down by `offset_y`.
+    ///
+    /// Negative offset scrolls the page up, so that its lower part is visible.
+    fn paint_display_list(&mut self, display_list: &DisplayList, offset_y: f32) {
+        for item in display_list {
+            self.paint_item(item, offset_y);
+        }
+    }
 
     /// Paint a tree of LayoutBoxes to an array of pixels.
     fn paint(&mut self, layout_root: NodeRef<'_, LayoutBox>) {
         let display_list = build_display_list(layout_root);
-        for item in display_list {
-            self.paint_item(&item);
-        }
+        self.paint_display_list(&display_list, 0.0);
     }
 }
 
 impl PixelBuffer for Pixmap {
-    fn paint_item(&mut self, item: &DisplayCommand) {
+    fn paint_item(&mut self, item: &DisplayCommand, offset_y: f32) {
         match item {
             DisplayCommand::SolidColor(color, rect) => {
+                // Boxes with negative size, e.g. due to negative margins, have nothing to paint.
+                let Some(rect) =
+                    tiny_skia::Rect::from_xywh(rect.x, rect.y, rect.width, rect.height)
+                else {
+                    return;
+                };
                 let mut paint = tiny_skia::Paint::default();
                 paint.set_color_rgba8(color.0, color.1, color.2, 255);
                 self.fill_rect(
-                    tiny_skia::Rect::from_xywh(rect.x, rect.y, rect.width, rect.height).unwrap(),
+                    rect,
                     &paint,
-                    tiny_skia::Transform::identity(),
+                    tiny_skia::Transform::from_translate(0.0, offset_y),
                     None,
                 );
             }
+            DisplayCommand::Text(run) => text::draw_text_run(self, run, offset_y),
         }
     }
 }
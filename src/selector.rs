@@ -7,13 +7,14 @@ pub use cssparser::ToCss;
 use html5ever::{LocalName, Namespace};
 use precomputed_hash::PrecomputedHash;
 use selectors::{
+    bloom::BloomFilter,
     matching,
-    parser::{self, ParseRelative, Selector, SelectorList, SelectorParseErrorKind},
+    parser::{self, AncestorHashes, ParseRelative, Selector, SelectorList, SelectorParseErrorKind},
 };
 
 use crate::html::element_ref::ElementNode;
 use crate::html::error::SelectorErrorKind;
-use crate::html::ElementRef;
+use crate::html::{Element, ElementRef};
 
 /// Wrapper around CSS selectors.
 ///
@@ -22,6 +23,8 @@ use crate::html::ElementRef;
 pub struct SelectorGroup {
     /// The CSS selectors.
     pub selectors: SelectorList<Simple>,
+    /// Hashes of ids, classes and names the ancestors must have for each selector to match.
+    ancestor_hashes: Vec<AncestorHashes>,
 }
 
 impl SelectorGroup {
@@ -30,9 +33,17 @@ impl SelectorGroup {
         let mut parser_input = cssparser::ParserInput::new(selectors);
         let mut parser = cssparser::Parser::new(&mut parser_input);
 
-        SelectorList::parse(&Parser, &mut parser, ParseRelative::No)
-            .map(|selectors| Self { selectors })
-            .map_err(SelectorErrorKind::from)
+        let selectors = SelectorList::parse(&Parser, &mut parser, ParseRelative::No)
+            .map_err(SelectorErrorKind::from)?;
+        let ancestor_hashes = selectors
+            .slice()
+            .iter()
+            .map(|s| AncestorHashes::new(s, matching::QuirksMode::NoQuirks))
+            .collect();
+        Ok(Self {
+            selectors,
+            ancestor_hashes,
+        })
     }
 
     /// Returns matching selector from simple selector list with highest specificity among matched in the list.
@@ -40,11 +51,40 @@ impl SelectorGroup {
         &self,
         element: &ElementRef<E>,
     ) -> Option<&Selector<Simple>> {
-        let mut caches = Default::default();
+        self.matching_selector_with(element, None, None, &mut Default::default())
+    }
+
+    /// Like [`SelectorGroup::matching_selector`], but for pseudo-element `pseudo` of the element.
+    /// Only selectors ending with the pseudo-element are considered.
+    pub fn matching_pseudo_selector<E: ElementNode + Clone>(
+        &self,
+        element: &ElementRef<E>,
+        pseudo: PseudoElement,
+    ) -> Option<&Selector<Simple>> {
+        self.matching_selector_with(element, Some(pseudo), None, &mut Default::default())
+    }
+
+    /// Matching selector with the highest specificity for the element, or its pseudo-element
+    /// `pseudo`, with `caches` shared between calls.
+    ///
+    /// If `bloom_filter` is given, it must contain [`ancestor_hashes`] of every ancestor of the
+    /// element. Selectors requiring an ancestor that is not in the filter are rejected without
+    /// walking up the tree.
+    pub fn matching_selector_with<E: ElementNode + Clone>(
+        &self,
+        element: &ElementRef<E>,
+        pseudo: Option<PseudoElement>,
+        bloom_filter: Option<&BloomFilter>,
+        caches: &mut matching::SelectorCaches,
+    ) -> Option<&Selector<Simple>> {
+        let mode = match pseudo {
+            Some(_) => matching::MatchingMode::ForStatelessPseudoElement,
+            None => matching::MatchingMode::Normal,
+        };
         let mut context = matching::MatchingContext::new(
-            matching::MatchingMode::Normal,
-            None,
-            &mut caches,
+            mode,
+            bloom_filter,
+            caches,
             matching::QuirksMode::NoQuirks,
             matching::NeedsSelectorFlags::No,
             matching::MatchingForInvalidation::No,
@@ -53,7 +93,12 @@ impl SelectorGroup {
         self.selectors
             .slice()
             .iter()
-            .filter(|&s| matching::matches_selector(s, 0, None, element, &mut context))
+            .zip(&self.ancestor_hashes)
+            .filter(|(s, _)| s.pseudo_element() == pseudo.as_ref())
+            .filter(|&(s, hashes)| {
+                matching::matches_selector(s, 0, Some(hashes), element, &mut context)
+            })
+            .map(|(s, _)| s)
             .max_by_key(|s| s.specificity())
     }
 
@@ -107,6 +152,23 @@ impl ToCss for SelectorGroup {
     }
 }
 
+/// Hashes of `element` to insert into a bloom filter while its descendants are matched, see
+/// [`SelectorGroup::matching_selector_with`].
+pub fn ancestor_hashes(element: &Element) -> Vec<u32> {
+    let mut hashes = vec![element.name.local.precomputed_hash()];
+    hashes.extend(
+        element
+            .id()
+            .map(|id| LocalName::from(id).precomputed_hash()),
+    );
+    hashes.extend(
+        element
+            .classes()
+            .map(|class| LocalName::from(class).precomputed_hash()),
+    );
+    hashes
+}
+
 /// An implementation of `Parser` for `selectors`
 #[derive(Clone, Copy, Debug)]
 pub struct Parser;
@@ -121,9 +183,33 @@ impl<'i> parser::Parser<'i> for Parser {
     fn parse_has(&self) -> bool {
         true
     }
+
+    fn parse_non_ts_pseudo_class(
+        &self,
+        location: cssparser::SourceLocation,
+        name: cssparser::CowRcStr<'i>,
+    ) -> Result<NonTSPseudoClass, cssparser::ParseError<'i, Self::Error>> {
+        NonTSPseudoClass::from_name(&name).ok_or_else(|| {
+            location.new_custom_error(SelectorParseErrorKind::UnsupportedPseudoClassOrElement(
+                name,
+            ))
+        })
+    }
+
+    fn parse_pseudo_element(
+        &self,
+        location: cssparser::SourceLocation,
+        name: cssparser::CowRcStr<'i>,
+    ) -> Result<PseudoElement, cssparser::ParseError<'i, Self::Error>> {
+        PseudoElement::from_name(&name).ok_or_else(|| {
+            location.new_custom_error(SelectorParseErrorKind::UnsupportedPseudoClassOrElement(
+                name,
+            ))
+        })
+    }
 }
 
-/// A simple implementation of `SelectorImpl` with no pseudo-classes or pseudo-elements.
+/// A simple implementation of `SelectorImpl` with state pseudo-classes and CSS 2.1 pseudo-elements.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Simple;
 
@@ -194,18 +280,58 @@ impl PrecomputedHash for CssLocalName {
 }
 
 /// Non Tree-Structural Pseudo-Class.
+///
+/// These match dynamic state of elements, see [`ElementState`](crate::html::node::ElementState).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum NonTSPseudoClass {}
+pub enum NonTSPseudoClass {
+    Hover,
+    Active,
+    Focus,
+    Link,
+    Visited,
+    Checked,
+    Disabled,
+    Enabled,
+}
+
+impl NonTSPseudoClass {
+    fn from_name(name: &str) -> Option<Self> {
+        cssparser::match_ignore_ascii_case! { name,
+            "hover" => Some(Self::Hover),
+            "active" => Some(Self::Active),
+            "focus" => Some(Self::Focus),
+            "link" => Some(Self::Link),
+            "visited" => Some(Self::Visited),
+            "checked" => Some(Self::Checked),
+            "disabled" => Some(Self::Disabled),
+            "enabled" => Some(Self::Enabled),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Hover => "hover",
+            Self::Active => "active",
+            Self::Focus => "focus",
+            Self::Link => "link",
+            Self::Visited => "visited",
+            Self::Checked => "checked",
+            Self::Disabled => "disabled",
+            Self::Enabled => "enabled",
+        }
+    }
+}
 
 impl parser::NonTSPseudoClass for NonTSPseudoClass {
     type Impl = Simple;
 
     fn is_active_or_hover(&self) -> bool {
-        false
+        matches!(self, Self::Hover | Self::Active)
     }
 
     fn is_user_action_state(&self) -> bool {
-        false
+        matches!(self, Self::Hover | Self::Active | Self::Focus)
     }
 }
 
@@ -214,13 +340,40 @@ impl ToCss for NonTSPseudoClass {
     where
         W: fmt::Write,
     {
-        dest.write_str("")
+        dest.write_char(':')?;
+        dest.write_str(self.name())
     }
 }
 
 /// CSS Pseudo-Element
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PseudoElement {}
+pub enum PseudoElement {
+    Before,
+    After,
+    FirstLine,
+    FirstLetter,
+}
+
+impl PseudoElement {
+    fn from_name(name: &str) -> Option<Self> {
+        cssparser::match_ignore_ascii_case! { name,
+            "before" => Some(Self::Before),
+            "after" => Some(Self::After),
+            "first-line" => Some(Self::FirstLine),
+            "first-letter" => Some(Self::FirstLetter),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Before => "before",
+            Self::After => "after",
+            Self::FirstLine => "first-line",
+            Self::FirstLetter => "first-letter",
+        }
+    }
+}
 
 impl parser::PseudoElement for PseudoElement {
     type Impl = Simple;
@@ -231,7 +384,8 @@ impl ToCss for PseudoElement {
     where
         W: fmt::Write,
     {
-        dest.write_str("")
+        dest.write_str("::")?;
+        dest.write_str(self.name())
     }
 }
 
@@ -281,4 +435,51 @@ mod tests {
         let s = ":where(a)";
         let _sel: SelectorGroup = s.try_into().unwrap();
     }
+
+    #[test]
+    fn state_pseudo_classes() {
+        let sel = SelectorGroup::parse("a:HOVER, input:checked:not(:disabled)").unwrap();
+        assert_eq!(sel.to_css_string(), "a:hover, input:checked:not(:disabled)");
+        assert!(SelectorGroup::parse("a:unknown").is_err());
+    }
+
+    #[test]
+    fn generated_content_pseudo_elements() {
+        let sel =
+            SelectorGroup::parse("p::before, p:AFTER, p:first-line, p::first-letter").unwrap();
+        assert_eq!(
+            sel.to_css_string(),
+            "p::before, p::after, p::first-line, p::first-letter"
+        );
+        assert!(SelectorGroup::parse("p::marker").is_err());
+    }
+
+    #[test]
+    fn bloom_filter_rejects_selectors_without_ancestors() {
+        let html = crate::html::Html::parse_fragment(r#"<div id="a" class="b"><p>x</p></div>"#);
+        let p = html
+            .select(&SelectorGroup::parse("p").unwrap())
+            .next()
+            .unwrap();
+        let mut caches = Default::default();
+        let mut bloom_filter = BloomFilter::new();
+        let mut matching = |selectors, bloom_filter: &BloomFilter| {
+            SelectorGroup::parse(selectors)
+                .unwrap()
+                .matching_selector_with(&p, None, Some(bloom_filter), &mut caches)
+                .map(|s| s.to_css_string())
+        };
+
+        // Empty filter claims there are no ancestors, so only selectors without them match.
+        assert_eq!(matching("div p, p", &bloom_filter), Some("p".into()));
+        for ancestor in p.ancestors().filter_map(ElementRef::wrap) {
+            for hash in ancestor_hashes(ancestor.value()) {
+                bloom_filter.insert_hash(hash);
+            }
+        }
+        assert_eq!(matching("div p, p", &bloom_filter), Some("div p".into()));
+        assert_eq!(matching("#a > p", &bloom_filter), Some("#a > p".into()));
+        assert_eq!(matching(".b p", &bloom_filter), Some(".b p".into()));
+        assert_eq!(matching("span p, .c p", &bloom_filter), None);
+    }
 }
@@ -3,17 +3,35 @@
 //! This is not very interesting at the moment.  It will get much more
 //! complicated if I add support for compound selectors.
 
+use std::ops::ControlFlow;
+
 use ego_tree::*;
+use selectors::bloom::BloomFilter;
+use selectors::matching::SelectorCaches;
 
-use crate::css::{props::*, Rule, StyleSheet};
+use crate::css::{self, media::Device, props::*, values, Rule, StyleSheet};
+use crate::html::node::Text;
 use crate::html::*;
+use crate::selector::{self, PseudoElement};
 
 // TODO: Rewrite with reference to the Node in other tree.
 /// A node with associated style data.
 #[derive(Clone, Debug, PartialEq)]
 pub struct StyledNode {
+    /// Id of the DOM node in the tree it was styled from. Pseudo-elements and their content have
+    /// id of the element they belong to.
+    pub id: NodeId,
     pub node: Node,
+    /// Pseudo-element the node was generated for, `None` for nodes of the DOM.
+    pub pseudo: Option<PseudoElement>,
     pub props: Props,
+    /// Computed values of the node on the first line of its block container, if `::first-line` of
+    /// the container, or of a block whose first line it is, has any rules. These values inherit
+    /// from the `::first-line` pseudo-element instead of the parent element. For a block with a
+    /// `::first-line` rule of its own, these are the values of the pseudo-element.
+    ///
+    /// Block-level nodes are on the first line only if nothing is displayed before them.
+    pub first_line: Option<Props>,
 }
 
 impl StyledNode {
@@ -33,17 +51,6 @@ impl StyledNode {
         self.get().cloned()
     }
 
-    /// Return the specified value of property `name`, or property `fallback_name` if that doesn't
-    /// exist, or value `default` if neither does.
-    pub fn lookup<T: Property + Clone, U: Property + Clone>(&self, f: fn(U) -> T, default: T) -> T
-    where
-        for<'a> &'a T: From<&'a PropUnion>,
-        for<'a> &'a U: From<&'a PropUnion>,
-    {
-        self.value::<T>()
-            .unwrap_or_else(move || self.value::<U>().map(f).unwrap_or_else(|| default))
-    }
-
     /// The value of the `display` property (defaults to inline).
     pub fn display(&self) -> Display {
         match self.value() {
@@ -57,47 +64,419 @@ impl StyledNode {
 
 /// Apply a stylesheet to an entire DOM tree, returning a StyledNode tree.
 ///
-/// This finds only the specified values at the moment. Eventually it should be extended to find the
-/// computed values too, including inherited values.
-pub fn style_tree(tree: &Tree<Node>, stylesheet: &StyleSheet) -> Tree<StyledNode> {
-    let f = |nr: NodeRef<Node>, stylesheet: &StyleSheet| {
+/// Nodes of the resulting tree carry computed values: every property has a value, either
+/// specified, inherited from the parent or initial. Text and other non-element nodes have no
+/// declarations of their own, so they get only inherited and initial values.
+///
+/// Author `stylesheet` is cascaded together with `user_stylesheet` and the user agent one. Rules
+/// with media queries apply only if the queries match `device`.
+pub fn style_tree(
+    tree: &Tree<Node>,
+    stylesheet: &StyleSheet,
+    user_stylesheet: &StyleSheet,
+    device: &Device,
+) -> Tree<StyledNode> {
+    let mut styler = Styler {
+        stylesheets: Origins {
+            user: user_stylesheet,
+            author: stylesheet,
+        },
+        device,
+        counters: Counters::default(),
+        matcher: Matcher {
+            bloom_filter: Some(BloomFilter::new()),
+            caches: SelectorCaches::default(),
+        },
+    };
+    let root = tree.root();
+    let mut root_styled = styler.style_node(root);
+    root_styled.props.compute(None);
+    let mut style_tree = Tree::new(root_styled);
+
+    // TODO: Optimize tree traversal to avoid recursion using algorithm of `NodeMut::for_each_descendant`.
+    styler.style_children(style_tree.root_mut(), root, true);
+
+    style_tree
+}
+
+/// State of the style tree traversal.
+struct Styler<'a> {
+    stylesheets: Origins<'a>,
+    device: &'a Device,
+    counters: Counters,
+    matcher: Matcher,
+}
+
+/// Selector matching state shared by elements.
+#[derive(Default)]
+struct Matcher {
+    /// Hashes of all ancestors of the element being styled. Without a filter selectors with
+    /// combinators are matched by walking up the tree only.
+    bloom_filter: Option<BloomFilter>,
+    caches: SelectorCaches,
+}
+
+impl Styler<'_> {
+    /// Node with specified values of `dom_node`.
+    fn style_node(&mut self, dom_node: NodeRef<Node>) -> StyledNode {
         StyledNode {
-            node: nr.value().clone(),
-            props: match ElementRef::wrap(nr) {
-                Some(er) => specified_values(&er, stylesheet),
-                _ => Props::new(), // Just ignore styling of other elements, e.g. text for now.
+            id: dom_node.id(),
+            node: dom_node.value().clone(),
+            pseudo: None,
+            props: match ElementRef::wrap(dom_node) {
+                Some(er) => {
+                    specified_values(&er, None, self.stylesheets, self.device, &mut self.matcher)
+                }
+                _ => Props::new(),
             },
+            first_line: None,
         }
-    };
+    }
 
-    fn style_tree_rec(
+    /// Style children of `dom_node` and append them to `style_node`, between `::before` and
+    /// `::after` pseudo-elements of the node if it is an element. Styles of `::first-line` are
+    /// stored on the node and its descendants, `::first-letter` wraps the first letter of the
+    /// children.
+    ///
+    /// Only `displayed` nodes, i.e. without `display: none` on themselves and their ancestors,
+    /// change counters.
+    fn style_children(
+        &mut self,
         mut style_node: NodeMut<StyledNode>,
         dom_node: NodeRef<Node>,
-        stylesheet: &StyleSheet,
-        f: fn(NodeRef<Node>, &StyleSheet) -> StyledNode,
+        displayed: bool,
     ) {
+        // Counters created by the children are in scope until the end of the node.
+        let scope = self.counters.0.len();
+        let element = ElementRef::wrap(dom_node);
+
+        if let Some(element) = &element {
+            if style_node.value().display() == Display::Block {
+                let mut first_line = specified_values(
+                    element,
+                    Some(PseudoElement::FirstLine),
+                    self.stylesheets,
+                    self.device,
+                    &mut self.matcher,
+                );
+                if !first_line.is_empty() {
+                    // The pseudo-element is on the first line of the parent block too, if any.
+                    let node = style_node.value();
+                    first_line.compute(Some(node.first_line.as_ref().unwrap_or(&node.props)));
+                    node.first_line = Some(first_line);
+                }
+            }
+            self.generate(&mut style_node, element, PseudoElement::Before, displayed);
+        }
+        // The element is an ancestor of everything styled until its children are done.
+        let hashes = element
+            .as_ref()
+            .map(|element| selector::ancestor_hashes(element.value()))
+            .unwrap_or_default();
+        if let Some(bloom_filter) = &mut self.matcher.bloom_filter {
+            hashes
+                .iter()
+                .for_each(|&hash| bloom_filter.insert_hash(hash));
+        }
+        let first_line = style_node.value().first_line.clone();
+        let mut starts_line = first_line.is_some() && starts_line(&mut style_node);
         for child in dom_node.children() {
-            style_tree_rec(
-                style_node.append(f(child, stylesheet)),
-                child,
-                stylesheet,
-                f,
-            )
+            let mut styled_child = self.style_node(child);
+            let child_first_line = on_first_line(&styled_child.props, first_line.as_ref());
+            styled_child.props.compute(Some(&style_node.value().props));
+            let display = styled_child.display();
+            if display == Display::Inline || starts_line {
+                styled_child.first_line = child_first_line;
+            }
+            starts_line &= !ends_line(&styled_child);
+            let displayed = displayed && display != Display::None;
+            if displayed {
+                self.counters.update(&styled_child.props);
+            }
+            self.style_children(style_node.append(styled_child), child, displayed);
+        }
+        if let Some(bloom_filter) = &mut self.matcher.bloom_filter {
+            hashes
+                .iter()
+                .for_each(|&hash| bloom_filter.remove_hash(hash));
         }
+        if let Some(element) = &element {
+            self.generate(&mut style_node, element, PseudoElement::After, displayed);
+            if style_node.value().display() == Display::Block {
+                self.first_letter(&mut style_node, element);
+            }
+        }
+
+        self.counters.0.truncate(scope);
     }
 
-    let root_value = tree.root();
-    let mut style_tree = Tree::new(f(root_value, stylesheet));
-    let style_root = style_tree.root_mut();
-    let root = tree.root();
+    /// Wrap the first letter of the block `style_node` into its `::first-letter` pseudo-element,
+    /// if any rule applies to it.
+    fn first_letter(&mut self, style_node: &mut NodeMut<StyledNode>, element: &ElementRef<Node>) {
+        let mut props = specified_values(
+            element,
+            Some(PseudoElement::FirstLetter),
+            self.stylesheets,
+            self.device,
+            &mut self.matcher,
+        );
+        if props.is_empty() {
+            return;
+        }
+        let id = style_node.id();
+        let tree = style_node.tree();
+        let ControlFlow::Break(Some(text_id)) = first_text(tree.get(id).unwrap()) else {
+            return;
+        };
+        let mut text_node = tree.get_mut(text_id).unwrap();
+        let Node::Text(text) = &text_node.value().node else {
+            unreachable!("first_text returns text nodes");
+        };
+        let (before, letter, after) = split_first_letter(text);
+        let (before, letter, after) = (before.to_owned(), letter.to_owned(), after.to_owned());
 
-    // TODO: Optimize tree traversal to avoid recursion using algorithm of `NodeMut::for_each_descendant`.
-    style_tree_rec(style_root, root, stylesheet, f);
+        // The letter is wrapped right inside of the text's parent, so it inherits from there.
+        let mut parent = text_node.parent().unwrap();
+        let letter_first_line = on_first_line(&props, parent.value().first_line.as_ref());
+        props.compute(Some(&parent.value().props));
+        let mut text_props = Props::new();
+        text_props.compute(Some(&props));
+        let text_first_line = on_first_line(&Props::new(), letter_first_line.as_ref());
+        let text_style = text_node.value().clone();
+        let styled_text = |text: String, pseudo, props, first_line| StyledNode {
+            id: element.id(),
+            node: Node::Text(Text { text: text.into() }),
+            pseudo,
+            props,
+            first_line,
+        };
 
-    style_tree
+        if !before.is_empty() {
+            text_node.insert_before(styled_text(
+                before,
+                None,
+                text_style.props,
+                text_style.first_line,
+            ));
+        }
+        text_node
+            .insert_before(StyledNode {
+                id: element.id(),
+                node: Node::Element(element.value().clone()),
+                pseudo: Some(PseudoElement::FirstLetter),
+                props,
+                first_line: letter_first_line,
+            })
+            .append(styled_text(
+                letter,
+                Some(PseudoElement::FirstLetter),
+                text_props,
+                text_first_line,
+            ));
+        if after.is_empty() {
+            text_node.detach();
+        } else if let Node::Text(text) = &mut text_node.value().node {
+            text.text = after.into();
+        }
+    }
+
+    /// Append pseudo-element `pseudo` of `element` with its content to `style_node`, which is the
+    /// node of the element. Nothing is appended if the pseudo-element has no content.
+    fn generate(
+        &mut self,
+        style_node: &mut NodeMut<StyledNode>,
+        element: &ElementRef<Node>,
+        pseudo: PseudoElement,
+        displayed: bool,
+    ) {
+        let mut props = specified_values(
+            element,
+            Some(pseudo),
+            self.stylesheets,
+            self.device,
+            &mut self.matcher,
+        );
+        // `content` is not inherited, so without a declaration it is `normal`.
+        if props.get::<Content>().is_none() {
+            return;
+        }
+        let first_line = on_first_line(&props, style_node.value().first_line.as_ref());
+        props.compute(Some(&style_node.value().props));
+        let mut pseudo_node = StyledNode {
+            id: element.id(),
+            node: Node::Element(element.value().clone()),
+            pseudo: Some(pseudo),
+            props,
+            first_line: None,
+        };
+        let Some(Content::ContentList(values::ContentList(items))) = pseudo_node.props.get() else {
+            return;
+        };
+        if pseudo_node.display() == Display::None {
+            return;
+        }
+        if first_line.is_some()
+            && (pseudo_node.display() == Display::Inline || starts_line(style_node))
+        {
+            pseudo_node.first_line = first_line;
+        }
+
+        if displayed {
+            self.counters.update(&pseudo_node.props);
+        }
+        let text: String = items
+            .iter()
+            .map(|item| self.counters.content(item, element.value()))
+            .collect();
+
+        let mut text_props = Props::new();
+        text_props.compute(Some(&pseudo_node.props));
+        let text_first_line = on_first_line(&Props::new(), pseudo_node.first_line.as_ref());
+        let mut node = style_node.append(pseudo_node);
+        if !text.is_empty() {
+            node.append(StyledNode {
+                id: element.id(),
+                node: Node::Text(Text { text: text.into() }),
+                pseudo: Some(pseudo),
+                props: text_props,
+                first_line: text_first_line,
+            });
+        }
+    }
+}
+
+/// Computed values of a node with `specified` values on the first line, where its parent has
+/// `parent_first_line` values. `None` if the parent is not on a first line styled by `::first-line`.
+fn on_first_line(specified: &Props, parent_first_line: Option<&Props>) -> Option<Props> {
+    let parent_first_line = parent_first_line?;
+    let mut props = specified.clone();
+    props.compute(Some(parent_first_line));
+    Some(props)
+}
+
+/// Whether a block-level node appended to `style_node` would be on its first line, i.e. nothing
+/// displayed precedes it.
+fn starts_line(style_node: &mut NodeMut<StyledNode>) -> bool {
+    let id = style_node.id();
+    let tree = style_node.tree();
+    tree.get(id)
+        .unwrap()
+        .children()
+        .all(|child| !ends_line(child.value()))
+}
+
+/// Whether `node` ends the first line for the blocks after it. Whitespace-only text collapses away
+/// before a block, so only the other displayed nodes do.
+fn ends_line(node: &StyledNode) -> bool {
+    match &node.node {
+        Node::Text(text) => !text.trim().is_empty(),
+        _ => node.display() != Display::None,
+    }
+}
+
+/// Search for the first displayed text node under `node` with anything but whitespace, in
+/// document order. Search breaks with `None` if the first letter is already wrapped into a
+/// `::first-letter` of a nested block.
+fn first_text(node: NodeRef<StyledNode>) -> ControlFlow<Option<NodeId>> {
+    for child in node.children() {
+        let style = child.value();
+        match &style.node {
+            Node::Text(text) if !text.trim().is_empty() => {
+                return ControlFlow::Break(Some(child.id()))
+            }
+            _ if style.pseudo == Some(PseudoElement::FirstLetter) => {
+                return ControlFlow::Break(None)
+            }
+            _ if style.display() == Display::None => continue,
+            _ => first_text(child)?,
+        }
+    }
+    ControlFlow::Continue(())
+}
+
+/// Split text into leading whitespace, the first letter and the rest. Punctuation before and
+/// right after the letter is a part of it, as in `"A`.
+fn split_first_letter(text: &str) -> (&str, &str, &str) {
+    fn is_punctuation(c: char) -> bool {
+        c.is_ascii_punctuation() || matches!(c, '«' | '»' | '‹' | '›' | '‘' | '’' | '“' | '”' | '„')
+    }
+
+    let start = text.len() - text.trim_start().len();
+    let mut end = start;
+    let mut letter_found = false;
+    for c in text[start..].chars() {
+        if is_punctuation(c) {
+            end += c.len_utf8();
+        } else if !letter_found && !c.is_whitespace() {
+            letter_found = true;
+            end += c.len_utf8();
+        } else {
+            break;
+        }
+    }
+    (&text[..start], &text[start..end], &text[end..])
+}
+
+/// Counters in scope during the style tree traversal, outermost first.
+#[derive(Default)]
+struct Counters(Vec<(String, i32)>);
+
+impl Counters {
+    /// Apply `counter-reset` and then `counter-increment` of a node.
+    fn update(&mut self, props: &Props) {
+        if let Some(CounterReset::CounterList(values::CounterList(counters))) = props.get() {
+            for (name, value) in counters {
+                self.0.push((name.clone(), value.unwrap_or(0)));
+            }
+        }
+        if let Some(CounterIncrement::CounterList(values::CounterList(counters))) = props.get() {
+            for (name, value) in counters {
+                *self.innermost(name) += value.unwrap_or(1);
+            }
+        }
+    }
+
+    /// Value of the innermost counter `name`. Counter not in scope is created, as if it was reset
+    /// by the current node.
+    fn innermost(&mut self, name: &str) -> &mut i32 {
+        let i = match self.0.iter().rposition(|(counter, _)| counter == name) {
+            Some(i) => i,
+            None => {
+                self.0.push((name.to_owned(), 0));
+                self.0.len() - 1
+            }
+        };
+        &mut self.0[i].1
+    }
+
+    /// Text generated by a `content` item of a pseudo-element of `element`.
+    fn content(&mut self, item: &values::ContentItem, element: &Element) -> String {
+        match item {
+            values::ContentItem::String(string) => string.clone(),
+            values::ContentItem::Attr(name) => element.attr(name).unwrap_or_default().to_owned(),
+            values::ContentItem::Counter(name, style) => style.format(*self.innermost(name)),
+            values::ContentItem::Counters(name, separator, style) => {
+                self.innermost(name);
+                self.0
+                    .iter()
+                    .filter(|(counter, _)| counter == name)
+                    .map(|&(_, value)| style.format(value))
+                    .collect::<Vec<_>>()
+                    .join(separator)
+            }
+            // Unsupported items are replaced with the initial value while parsing.
+            _ => String::new(),
+        }
+    }
+}
+
+/// Style sheets of the user and the author. User agent style sheet is always the default one.
+#[derive(Clone, Copy)]
+struct Origins<'a> {
+    user: &'a StyleSheet,
+    author: &'a StyleSheet,
 }
 
-// TODO: Allow user stylesheet. Don't forget to change doc comment below.
 /// Apply styles to a single element, returning the specified styles.
 ///
 /// This is place where cascade part of CSS implemented.
@@ -108,18 +487,30 @@ pub fn style_tree(tree: &Tree<Node>, stylesheet: &StyleSheet) -> Tree<StyledNode
 /// element, then sorts different sources by importance and applies
 /// most important on top of less important.
 ///
-/// Specifically, in ascending order of precedence (omitting user stylesheets):
+/// Specifically, in ascending order of precedence:
 ///
 /// 1. user agent declarations
 /// 1. user normal declarations
 /// 1. author normal declarations
 /// 1. author important declarations
 /// 1. user important declarations
-fn specified_values(elem: &ElementRef<Node>, stylesheet: &StyleSheet) -> Props {
+///
+/// Declarations of the `style` attribute are author declarations more specific than any
+/// selector, so they go after the rules of the same importance.
+///
+/// If `pseudo` is set, styles of the pseudo-element of the element are returned instead. The
+/// `style` attribute does not apply to pseudo-elements.
+fn specified_values(
+    elem: &ElementRef<Node>,
+    pseudo: Option<PseudoElement>,
+    stylesheets: Origins,
+    device: &Device,
+    matcher: &mut Matcher,
+) -> Props {
     let mut props = Props::new();
     // Apply User Agent declarations
     let user_agent_stylesheet = &*crate::css::DEAFULT_STYLESHEET;
-    let mut user_agent_rules = matching_rules(elem, user_agent_stylesheet);
+    let mut user_agent_rules = matching_rules(elem, pseudo, user_agent_stylesheet, device, matcher);
     user_agent_rules.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
     user_agent_rules.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
     for (_, rule) in user_agent_rules {
@@ -128,8 +519,15 @@ fn specified_values(elem: &ElementRef<Node>, stylesheet: &StyleSheet) -> Props {
         props.extend(rule_props);
     }
 
+    // Apply User declarations
+    let mut user_rules = matching_rules(elem, pseudo, stylesheets.user, device, matcher);
+    user_rules.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
+    for (_, rule) in &user_rules {
+        props.extend(&rule.declarations);
+    }
+
     // Get Author declarations
-    let mut rules = matching_rules(elem, stylesheet);
+    let mut rules = matching_rules(elem, pseudo, stylesheets.author, device, matcher);
     // Go through the rules from lowest to highest specificity.
     rules.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
 
@@ -140,38 +538,344 @@ fn specified_values(elem: &ElementRef<Node>, stylesheet: &StyleSheet) -> Props {
     // ```
     // Will make div inline if we merge those loops.
 
+    // Diagnostics of inline styles are reported once, by `css::from_document`.
+    let inline_style = elem
+        .attr("style")
+        .filter(|_| pseudo.is_none())
+        .map(|style| css::parse_inline_style(style).0);
+
     // Assign regular properties
     for (_, rule) in &rules {
         let rule_props = &rule.declarations;
         props.extend(rule_props);
     }
+    if let Some(inline_style) = &inline_style {
+        props.extend(&inline_style.declarations);
+    }
 
     // Override regular author properties with important ones.
     for (_, rule) in &rules {
         let important_rule_props = &rule.important_declarations;
         props.extend(important_rule_props);
     }
+    if let Some(inline_style) = &inline_style {
+        props.extend(&inline_style.important_declarations);
+    }
+
+    // Important user declarations override everything, e.g. to enforce readable font sizes.
+    for (_, rule) in &user_rules {
+        props.extend(&rule.important_declarations);
+    }
 
+    if let Some(pseudo) = pseudo {
+        props.retain(|idx| applies_to(pseudo, idx));
+    }
     props
 }
 
+/// Whether property `idx` applies to `pseudo`. Following CSS 2.1, `::first-line` takes font,
+/// color and background properties, `::first-letter` also takes margins, paddings and borders.
+fn applies_to(pseudo: PseudoElement, idx: PropIndex) -> bool {
+    const FONT: [PropIndex; 3] = [Color::ID, FontFamily::ID, FontSize::ID];
+    const BACKGROUND: [PropIndex; 5] = [
+        BackgroundAttachment::ID,
+        BackgroundColor::ID,
+        BackgroundImage::ID,
+        BackgroundPosition::ID,
+        BackgroundRepeat::ID,
+    ];
+    const BOX: [PropIndex; 16] = [
+        MarginTop::ID,
+        MarginRight::ID,
+        MarginBottom::ID,
+        MarginLeft::ID,
+        PaddingTop::ID,
+        PaddingRight::ID,
+        PaddingBottom::ID,
+        PaddingLeft::ID,
+        BorderTopWidth::ID,
+        BorderRightWidth::ID,
+        BorderBottomWidth::ID,
+        BorderLeftWidth::ID,
+        BorderTopColor::ID,
+        BorderRightColor::ID,
+        BorderBottomColor::ID,
+        BorderLeftColor::ID,
+    ];
+    match pseudo {
+        PseudoElement::Before | PseudoElement::After => true,
+        PseudoElement::FirstLine => FONT.contains(&idx) || BACKGROUND.contains(&idx),
+        PseudoElement::FirstLetter => {
+            FONT.contains(&idx) || BACKGROUND.contains(&idx) || BOX.contains(&idx)
+        }
+    }
+}
+
 type Specificity = u32;
 /// A single CSS rule and the specificity of its most specific matching selector.
 type MatchedRule<'a> = (Specificity, &'a Rule);
 
-/// Find all CSS rules that match the given element.
-fn matching_rules<'a>(elem: &ElementRef<Node>, stylesheet: &'a StyleSheet) -> Vec<MatchedRule<'a>> {
-    // For now, we just do a linear scan of all the rules.  For large
-    // documents, it would be more efficient to store the rules in hash tables
-    // based on tag name, id, class, etc.
+/// Find all CSS rules that match the given element, or its pseudo-element `pseudo`, on `device`.
+fn matching_rules<'a>(
+    elem: &ElementRef<Node>,
+    pseudo: Option<PseudoElement>,
+    stylesheet: &'a StyleSheet,
+    device: &Device,
+    matcher: &mut Matcher,
+) -> Vec<MatchedRule<'a>> {
     stylesheet
-        .iter()
-        .filter_map(|rule| match_rule(elem, rule))
+        .candidates(elem.value())
+        .filter(|rule| rule.matches_media(device))
+        .filter_map(|rule| match_rule(elem, pseudo, rule, matcher))
         .collect()
 }
 
-/// If `rule` matches `elem`, return a `MatchedRule`. Otherwise return `None`.
-fn match_rule<'a>(elem: &ElementRef<Node>, rule: &'a Rule) -> Option<MatchedRule<'a>> {
-    let highest_specificity_matching_selector = rule.selectors.matching_selector(elem);
+/// If `rule` matches `elem`, or its pseudo-element `pseudo`, return a `MatchedRule`. Otherwise
+/// return `None`.
+fn match_rule<'a>(
+    elem: &ElementRef<Node>,
+    pseudo: Option<PseudoElement>,
+    rule: &'a Rule,
+    matcher: &mut Matcher,
+) -> Option<MatchedRule<'a>> {
+    let highest_specificity_matching_selector = rule.selectors.matching_selector_with(
+        elem,
+        pseudo,
+        matcher.bloom_filter.as_ref(),
+        &mut matcher.caches,
+    );
     highest_specificity_matching_selector.map(|s| (s.specificity(), rule))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::values::Color as ColorV;
+    use crate::text::TextStyle;
+
+    fn styled(source: &str) -> Tree<StyledNode> {
+        let html = Html::parse_fragment(source);
+        let stylesheet = css::from_document(&html, None, &crate::loader::FileLoader).0;
+        style_tree(
+            &html.tree,
+            &stylesheet,
+            &StyleSheet::new(),
+            &Device::default(),
+        )
+    }
+
+    fn color_of(html: &str, selector: &str) -> Option<Color> {
+        color_with_user_style(html, "", selector)
+    }
+
+    fn color_with_user_style(html: &str, user_css: &str, selector: &str) -> Option<Color> {
+        let html = Html::parse_fragment(html);
+        let stylesheets = Origins {
+            user: &css::parse_stylesheet(user_css).0,
+            author: &css::from_document(&html, None, &crate::loader::FileLoader).0,
+        };
+        let selector = crate::selector::SelectorGroup::parse(selector).unwrap();
+        let elem = html.select(&selector).next().unwrap();
+        specified_values(
+            &elem,
+            None,
+            stylesheets,
+            &Device::default(),
+            &mut Matcher::default(),
+        )
+        .get::<Color>()
+        .cloned()
+    }
+
+    #[test]
+    fn first_letter_and_first_line_take_restricted_properties() {
+        let source = r#"<style>
+            p { display: block; }
+            p::first-letter { color: red; display: block; margin-left: 5px; }
+            p::first-line { color: blue; width: 10px; background-color: blue; }
+            div::first-letter { color: green; }
+        </style><p> "Hello</p><div><p>a</p>b</div>"#;
+        let tree = styled(source);
+        let root = tree.root();
+
+        let texts: Vec<_> = root
+            .descendants()
+            .filter_map(|node| match &node.value().node {
+                Node::Text(text) => Some((node.value().pseudo, &*text.text)),
+                _ => None,
+            })
+            .skip(1)
+            .collect();
+        // The first letter of the div is already a first letter of the nested paragraph.
+        let first_letter = Some(PseudoElement::FirstLetter);
+        assert_eq!(
+            texts,
+            [
+                (None, " "),
+                (first_letter, "\"H"),
+                (None, "ello"),
+                (first_letter, "a"),
+                (None, "b")
+            ]
+        );
+
+        let letter = root
+            .descendants()
+            .find(|node| node.value().pseudo == first_letter)
+            .unwrap()
+            .value();
+        assert_eq!(letter.display(), Display::Inline);
+        assert_eq!(letter.get(), Some(&Color::Color(ColorV(255, 0, 0))));
+        // Root has the initial margin.
+        assert_ne!(letter.get::<MarginLeft>(), root.value().get::<MarginLeft>());
+
+        let first_line = root
+            .descendants()
+            .find_map(|node| node.value().first_line.as_ref())
+            .unwrap();
+        assert_eq!(first_line.get(), Some(&Color::Color(ColorV(0, 0, 255))));
+        assert_eq!(first_line.get(), Some(&Width::Auto));
+        assert_eq!(
+            first_line.get(),
+            Some(&BackgroundColor::Color(ColorV(0, 0, 255)))
+        );
+    }
+
+    #[test]
+    fn first_line_values_inherit_from_first_line() {
+        let source = r#"<style>
+            div { display: block; font-size: 10px; }
+            section { display: block; }
+            div::first-line { font-size: 20px; }
+            span { font-size: 10px; }
+            em { font-size: 2em; }
+        </style><div><span>a</span><em>b</em><section>c</section></div>
+        <div><section>d</section></div>
+        <div>
+          <section>e</section>
+        </div>"#;
+        let tree = styled(source);
+
+        let font_sizes = |name: &str| -> Vec<_> {
+            tree.root()
+                .descendants()
+                .map(|node| node.value())
+                .filter(|node| matches!(&node.node, Node::Element(e) if e.name() == name))
+                .map(|node| {
+                    let first_line = node.first_line.as_ref();
+                    first_line.map(|props| TextStyle::from_props(props).font_size)
+                })
+                .collect()
+        };
+        // Declared values are kept, relative ones are resolved against the first line.
+        assert_eq!(font_sizes("span"), [Some(10.0)]);
+        assert_eq!(font_sizes("em"), [Some(40.0)]);
+        // A block is on the first line only if nothing is displayed before it.
+        assert_eq!(font_sizes("section"), [None, Some(20.0), Some(20.0)]);
+        assert_eq!(font_sizes("div"), [Some(20.0), Some(20.0), Some(20.0)]);
+    }
+
+    #[test]
+    fn pseudo_elements_generate_content() {
+        let source = r#"<style>
+            ol { counter-reset: item }
+            li { display: block; counter-increment: item }
+            li::before { content: counters(item, ".") ". " attr(title) }
+            li::after { content: none }
+            li li::after { content: ""; color: red }
+            ol ol::after { content: "hidden"; display: none }
+        </style><ol><li title="a" style="content: 'x'">x</li><li><ol><li>y</li></ol></li></ol>"#;
+        let tree = styled(source);
+
+        let generated: Vec<_> = tree
+            .root()
+            .descendants()
+            .filter_map(|node| Some((node.value().pseudo?, node)))
+            .map(|(pseudo, node)| match &node.value().node {
+                Node::Text(text) => (pseudo, text.to_string()),
+                _ => (pseudo, String::new()),
+            })
+            .collect();
+        let texts = |pseudo| {
+            generated
+                .iter()
+                .filter(|&&(p, _)| p == pseudo)
+                .map(|(_, text)| text.as_str())
+                .collect::<Vec<_>>()
+        };
+        // Pseudo-elements without text have boxes too.
+        assert_eq!(
+            texts(PseudoElement::Before),
+            ["", "1. a", "", "2. ", "", "2.1. "]
+        );
+        assert_eq!(texts(PseudoElement::After), [""]);
+
+        let after = tree
+            .root()
+            .descendants()
+            .find(|node| node.value().pseudo == Some(PseudoElement::After))
+            .unwrap();
+        assert_eq!(
+            after.value().get::<Color>(),
+            Some(&Color::Color(ColorV(255, 0, 0)))
+        );
+        assert_eq!(
+            after.parent().unwrap().last_child().unwrap().id(),
+            after.id()
+        );
+    }
+
+    #[test]
+    fn ancestors_leave_scope_after_their_children() {
+        let source = r#"<style>
+            p { color: red; }
+            .a p, #b > p { color: blue; }
+        </style><div class="a"><p>x</p></div><p id="c">y</p><div id="b"><p id="d">z</p></div>"#;
+        let tree = styled(source);
+
+        let colors: Vec<_> = tree
+            .root()
+            .descendants()
+            .filter(|node| matches!(&node.value().node, Node::Element(e) if e.name() == "p"))
+            .map(|node| node.value().get::<Color>().cloned())
+            .collect();
+        let (red, blue) = (ColorV(255, 0, 0), ColorV(0, 0, 255));
+        assert_eq!(colors, [blue, red, blue].map(|c| Some(Color::Color(c))));
+    }
+
+    #[test]
+    fn inline_style_overrides_author_rules() {
+        let source = r#"<style>#p { color: red; }</style><p id="p" style="color: blue">x</p>"#;
+        assert_eq!(color_of(source, "p"), Some(Color::Color(ColorV(0, 0, 255))));
+    }
+
+    #[test]
+    fn important_author_rule_overrides_inline_style() {
+        let source = r#"<style>p { color: red !important; }</style><p style="color: blue">x</p>"#;
+        assert_eq!(color_of(source, "p"), Some(Color::Color(ColorV(255, 0, 0))));
+
+        let source = r#"<style>p { color: red !important; }</style>
+            <p style="color: blue !important; display: block">x</p>"#;
+        assert_eq!(color_of(source, "p"), Some(Color::Color(ColorV(0, 0, 255))));
+    }
+
+    #[test]
+    fn user_rules_are_overridden_by_author_unless_important() {
+        let source = r#"<style>p { color: red; }</style><p>x</p>"#;
+        assert_eq!(
+            color_with_user_style(source, "p { color: blue; }", "p"),
+            Some(Color::Color(ColorV(255, 0, 0)))
+        );
+        assert_eq!(
+            color_with_user_style("<p>x</p>", "p { color: blue; }", "p"),
+            Some(Color::Color(ColorV(0, 0, 255)))
+        );
+
+        let source = r#"<style>p { color: red !important; }</style>
+            <p style="color: green !important">x</p>"#;
+        assert_eq!(
+            color_with_user_style(source, "* { color: blue !important; }", "p"),
+            Some(Color::Color(ColorV(0, 0, 255)))
+        );
+    }
+}
@@ -0,0 +1,338 @@
+//! Text shaping and rasterization on top of [cosmic-text](https://github.com/pop-os/cosmic-text).
+//!
+//! Fonts are loaded once from the system font directories, glyphs missing in the requested family
+//! are taken from fallback fonts by cosmic-text.
+use std::sync::{LazyLock, Mutex};
+
+use cosmic_text::{
+    Attrs, Buffer, Family, FontSystem, LayoutGlyph, Metrics, Shaping, SwashCache, SwashContent,
+    Wrap,
+};
+use log::warn;
+use tiny_skia::{Pixmap, PixmapPaint, Transform};
+
+use crate::css::{
+    props::{Color as ColorProp, FontFamily, FontSize, Props},
+    values::{self, AbsoluteSize, Color, FamilyName},
+};
+use crate::style::StyledNode;
+
+/// Ratio between line height and font size used for `line-height: normal`.
+pub const NORMAL_LINE_HEIGHT: f32 = 1.2;
+
+/// Fonts available for shaping.
+pub static FONT_SYSTEM: LazyLock<Mutex<FontSystem>> = LazyLock::new(|| {
+    let font_system = FontSystem::new();
+    if font_system.db().is_empty() {
+        warn!("No fonts found on the system, text will not be rendered");
+    }
+    Mutex::new(font_system)
+});
+
+/// Rasterized glyphs, shared between all paints.
+static SWASH_CACHE: LazyLock<Mutex<SwashCache>> = LazyLock::new(|| Mutex::new(SwashCache::new()));
+
+/// Properties of a node that affect how its text looks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    pub color: Color,
+    /// Families in order of preference.
+    pub font_family: Vec<FamilyName>,
+    /// Font size in px.
+    pub font_size: f32,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            color: Color(0, 0, 0),
+            font_family: vec![FamilyName::Serif],
+            font_size: AbsoluteSize::Medium.to_px(),
+        }
+    }
+}
+
+impl TextStyle {
+    /// Text style of the styled node, missing properties take their initial values.
+    pub fn new(style: &StyledNode) -> Self {
+        Self::from_props(&style.props)
+    }
+
+    /// Text style of the properties, missing ones take their initial values.
+    pub fn from_props(style: &Props) -> Self {
+        let default = Self::default();
+
+        let color = match style.get::<ColorProp>() {
+            Some(ColorProp::Color(color)) => *color,
+            _ => default.color,
+        };
+
+        let font_family = match style.get::<FontFamily>() {
+            Some(FontFamily::FontFamily(values::FontFamily(families))) => families.clone(),
+            _ => default.font_family,
+        };
+
+        let font_size = style
+            .get::<FontSize>()
+            .map_or(default.font_size, |size| size.to_px(default.font_size));
+
+        Self {
+            color,
+            font_family,
+            font_size,
+        }
+    }
+
+    /// Height of a line of text in this style.
+    pub fn line_height(&self) -> f32 {
+        self.font_size * NORMAL_LINE_HEIGHT
+    }
+
+    fn metrics(&self) -> Metrics {
+        Metrics::new(self.font_size, self.line_height())
+    }
+
+    fn attrs<'a>(&'a self, font_system: &FontSystem) -> Attrs<'a> {
+        let Color(r, g, b) = self.color;
+        Attrs::new()
+            .family(family(font_system, &self.font_family))
+            .color(cosmic_text::Color::rgb(r, g, b))
+            .metrics(self.metrics())
+    }
+}
+
+/// Load the font bundled for tests, so text is shaped the same on machines without fonts. Tests
+/// select it with `font-family: Tuffy`.
+#[cfg(test)]
+pub(crate) fn load_test_font() {
+    static LOADED: std::sync::Once = std::sync::Once::new();
+    LOADED.call_once(|| {
+        let font = include_bytes!("../tests/fonts/Tuffy.ttf");
+        FONT_SYSTEM
+            .lock()
+            .unwrap()
+            .db_mut()
+            .load_font_data(font.to_vec());
+    });
+}
+
+/// Pick the first family from the list that is installed. Generic families are always available.
+fn family<'a>(font_system: &FontSystem, families: &'a [FamilyName]) -> Family<'a> {
+    families
+        .iter()
+        .find_map(|name| match name {
+            FamilyName::Serif => Some(Family::Serif),
+            FamilyName::SansSerif => Some(Family::SansSerif),
+            FamilyName::Cursive => Some(Family::Cursive),
+            FamilyName::Fantasy => Some(Family::Fantasy),
+            FamilyName::Monospace => Some(Family::Monospace),
+            FamilyName::Named(name) => font_system
+                .db()
+                .faces()
+                .any(|face| {
+                    face.families
+                        .iter()
+                        .any(|(family, _)| family.eq_ignore_ascii_case(name))
+                })
+                .then_some(Family::Name(name)),
+        })
+        .unwrap_or(Family::Serif)
+}
+
+/// Glyphs of a single inline box on a single line.
+#[derive(Clone, Debug)]
+pub struct TextRun {
+    /// Position of the start of the run.
+    pub x: f32,
+    /// Position of the baseline.
+    pub baseline: f32,
+    /// Glyphs positioned relative to the start of the run on the baseline.
+    pub glyphs: Vec<LayoutGlyph>,
+}
+
+/// Vertical metrics of a font in px.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FontMetrics {
+    /// Distance from the baseline to the top of the glyphs.
+    pub ascent: f32,
+    /// Distance from the baseline to the bottom of the glyphs.
+    pub descent: f32,
+}
+
+/// Smallest piece of text a line can be broken after: a word together with spaces that follow it.
+#[derive(Clone, Debug, Default)]
+pub struct ShapedWord {
+    /// Glyphs positioned relative to the start of the word.
+    pub glyphs: Vec<LayoutGlyph>,
+    /// Advance of the word including trailing spaces.
+    pub width: f32,
+    /// Advance of trailing spaces, they are not visible at the end of a line.
+    pub space_width: f32,
+}
+
+/// Shape `text` and split it into words at spaces.
+///
+/// Text is expected to have its whitespace collapsed already, see [`collapse_whitespace`].
+pub fn shape_words(text: &str, style: &TextStyle) -> (Vec<ShapedWord>, FontMetrics) {
+    let mut font_system = FONT_SYSTEM.lock().unwrap();
+
+    let mut buffer = Buffer::new(&mut font_system, style.metrics());
+    buffer.set_wrap(&mut font_system, Wrap::None);
+    let attrs = style.attrs(&font_system);
+    buffer.set_text(&mut font_system, text, attrs, Shaping::Advanced);
+
+    let Some(line) = buffer
+        .line_layout(&mut font_system, 0)
+        .and_then(|lines| lines.first())
+    else {
+        return Default::default();
+    };
+
+    let metrics = FontMetrics {
+        ascent: line.max_ascent,
+        descent: line.max_descent,
+    };
+
+    let mut words: Vec<ShapedWord> = Vec::new();
+    let mut after_space = false;
+    for glyph in &line.glyphs {
+        let is_space = text[glyph.start..glyph.end].chars().all(|c| c == ' ');
+        // Next word starts with the first non space glyph after a space.
+        if words.is_empty() || (after_space && !is_space) {
+            words.push(ShapedWord::default());
+        }
+        after_space = is_space;
+
+        let word = words.last_mut().unwrap();
+        let mut glyph = glyph.clone();
+        glyph.x = word.width;
+        word.width += glyph.w;
+        if is_space {
+            word.space_width += glyph.w;
+        }
+        word.glyphs.push(glyph);
+    }
+
+    (words, metrics)
+}
+
+/// Metrics of the font text in `style` would be shaped with.
+pub fn font_metrics(style: &TextStyle) -> FontMetrics {
+    shape_words(" ", style).1
+}
+
+/// Collapse whitespace in spans of text as `white-space: normal` does.
+///
+/// Runs of whitespace become a single space, even across span boundaries, and whitespace at the
+/// start and the end of the paragraph is removed.
+pub fn collapse_whitespace<S>(spans: impl IntoIterator<Item = (String, S)>) -> Vec<(String, S)> {
+    let mut collapsed: Vec<(String, S)> = Vec::new();
+    // Start as if preceded by a space, so leading whitespace is dropped.
+    let mut after_space = true;
+
+    for (text, style) in spans {
+        let mut span = String::with_capacity(text.len());
+        for c in text.chars() {
+            if c.is_ascii_whitespace() {
+                if !after_space {
+                    span.push(' ');
+                }
+                after_space = true;
+            } else {
+                span.push(c);
+                after_space = false;
+            }
+        }
+        collapsed.push((span, style));
+    }
+
+    if let Some((last, _)) = collapsed
+        .iter_mut()
+        .rev()
+        .find(|(span, _)| !span.is_empty())
+    {
+        if last.ends_with(' ') {
+            last.pop();
+        }
+    }
+    collapsed.retain(|(span, _)| !span.is_empty());
+
+    collapsed
+}
+
+/// Rasterize glyphs of the run and draw them on `pixmap`, moved down by `offset_y`.
+pub fn draw_text_run(pixmap: &mut Pixmap, run: &TextRun, offset_y: f32) {
+    let mut font_system = FONT_SYSTEM.lock().unwrap();
+    let mut swash_cache = SWASH_CACHE.lock().unwrap();
+
+    for glyph in &run.glyphs {
+        let physical = glyph.physical((run.x, run.baseline + offset_y), 1.0);
+        let Some(image) = swash_cache.get_image(&mut font_system, physical.cache_key) else {
+            continue;
+        };
+        let (width, height) = (image.placement.width, image.placement.height);
+        let Some(mut glyph_pixmap) = Pixmap::new(width, height) else {
+            // Glyphs without outline, e.g. spaces.
+            continue;
+        };
+
+        let (r, g, b, a) = glyph
+            .color_opt
+            .map_or((0, 0, 0, 255), |color| color.as_rgba_tuple());
+        let pixels = glyph_pixmap.pixels_mut();
+        match image.content {
+            SwashContent::Mask => {
+                for (pixel, &coverage) in pixels.iter_mut().zip(&image.data) {
+                    let alpha = (coverage as u16 * a as u16 / 255) as u8;
+                    *pixel = tiny_skia::ColorU8::from_rgba(r, g, b, alpha).premultiply();
+                }
+            }
+            SwashContent::Color => {
+                for (pixel, rgba) in pixels.iter_mut().zip(image.data.chunks_exact(4)) {
+                    *pixel = tiny_skia::ColorU8::from_rgba(rgba[0], rgba[1], rgba[2], rgba[3])
+                        .premultiply();
+                }
+            }
+            SwashContent::SubpixelMask => {
+                // Not requested by `SwashCache`, so never produced.
+                warn!("Subpixel glyph masks are not supported");
+                continue;
+            }
+        }
+
+        pixmap.draw_pixmap(
+            physical.x + image.placement.left,
+            physical.y - image.placement.top,
+            glyph_pixmap.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            None,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whitespace_collapses_across_spans() {
+        let spans = vec![
+            ("\n  Hello ".to_string(), 0),
+            (" \t".to_string(), 1),
+            ("  big\n world ".to_string(), 2),
+            ("  ".to_string(), 3),
+        ];
+
+        assert_eq!(
+            collapse_whitespace(spans),
+            vec![("Hello ".to_string(), 0), ("big world".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn whitespace_only_text_is_dropped() {
+        let spans = vec![("\n    ".to_string(), ())];
+        assert!(collapse_whitespace(spans).is_empty());
+    }
+}